@@ -0,0 +1,216 @@
+// TrguiNG - next gen remote GUI for transmission torrent daemon
+// Copyright (C) 2023  qu1ck (mail at qu1ck.org)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use serde_json::{json, Value};
+use tauri::{async_runtime, AppHandle, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::appconfig::AppConfig;
+use crate::clipboard::is_torrent_url;
+use crate::httpclient::HttpClients;
+use crate::ipc::{add_without_dialog, show_notification, AddArgs};
+use crate::rpc::Rpc;
+use crate::session::TransmissionSessions;
+
+// Speed limit presets in KiB/s
+const SPEED_PRESETS: [u64; 5] = [100, 500, 1024, 5 * 1024, 10 * 1024];
+
+// Server wide actions that run in the backend, so they work with the main window closed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ServerAction {
+    PauseAll,
+    ResumeAll,
+    ToggleAltSpeed,
+    // None removes the limit
+    DownloadLimit(Option<u64>),
+    UploadLimit(Option<u64>),
+    AddFromClipboard,
+}
+
+impl ServerAction {
+    // Menu item id of the action
+    pub fn id(&self) -> String {
+        match self {
+            ServerAction::PauseAll => "pauseall".to_string(),
+            ServerAction::ResumeAll => "resumeall".to_string(),
+            ServerAction::ToggleAltSpeed => "altspeed".to_string(),
+            ServerAction::DownloadLimit(limit) => format!("limitdown-{}", limit.unwrap_or(0)),
+            ServerAction::UploadLimit(limit) => format!("limitup-{}", limit.unwrap_or(0)),
+            ServerAction::AddFromClipboard => "addclipboard".to_string(),
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        let limit = |value: &str| {
+            value
+                .parse::<u64>()
+                .ok()
+                .map(|l| Some(l).filter(|l| *l > 0))
+        };
+        match id {
+            "pauseall" => Some(ServerAction::PauseAll),
+            "resumeall" => Some(ServerAction::ResumeAll),
+            "altspeed" => Some(ServerAction::ToggleAltSpeed),
+            "addclipboard" => Some(ServerAction::AddFromClipboard),
+            _ => {
+                if let Some(value) = id.strip_prefix("limitdown-") {
+                    limit(value).map(ServerAction::DownloadLimit)
+                } else if let Some(value) = id.strip_prefix("limitup-") {
+                    limit(value).map(ServerAction::UploadLimit)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+// Unlimited followed by the presets
+pub fn speed_presets() -> impl Iterator<Item = Option<u64>> {
+    std::iter::once(None).chain(SPEED_PRESETS.iter().copied().map(Some))
+}
+
+pub fn speed_label(limit: Option<u64>) -> String {
+    match limit {
+        None => "Unlimited".to_string(),
+        Some(limit) if limit >= 1024 && limit % 1024 == 0 => format!("{} MiB/s", limit / 1024),
+        Some(limit) => format!("{limit} KiB/s"),
+    }
+}
+
+// Runs the action against the named server or the active one, the result is
+// reported with a notification.
+pub fn spawn(app: &AppHandle, server: Option<String>, action: ServerAction) {
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        if let Err(e) = run(&app, server.as_deref(), action).await {
+            show_notification(&app, "Action failed", &e);
+        }
+    });
+}
+
+async fn run(app: &AppHandle, server: Option<&str>, action: ServerAction) -> Result<(), String> {
+    let config = AppConfig::load(app)?;
+    let connection = config
+        .server_connection(server)
+        .ok_or_else(|| format!("Server {:?} not found", server.unwrap_or("")))?;
+    let clients = app.state::<HttpClients>();
+    let sessions = app.state::<TransmissionSessions>();
+    let rpc = Rpc::new(&clients, &sessions, &connection);
+
+    let message = match action {
+        // Adding reports each torrent with its own notification
+        ServerAction::AddFromClipboard => return add_from_clipboard(app, server).await,
+        ServerAction::PauseAll => {
+            rpc.call("torrent-stop", json!({})).await?;
+            "All torrents paused".to_string()
+        }
+        ServerAction::ResumeAll => {
+            rpc.call("torrent-start", json!({})).await?;
+            "All torrents resumed".to_string()
+        }
+        ServerAction::ToggleAltSpeed => {
+            let session = rpc
+                .call("session-get", json!({ "fields": ["alt-speed-enabled"] }))
+                .await?;
+            let enabled = !session["alt-speed-enabled"].as_bool().unwrap_or(false);
+            rpc.call("session-set", json!({ "alt-speed-enabled": enabled }))
+                .await?;
+            if enabled {
+                "Alternative speed limits enabled".to_string()
+            } else {
+                "Alternative speed limits disabled".to_string()
+            }
+        }
+        ServerAction::DownloadLimit(limit) => {
+            rpc.call("session-set", limit_arguments("speed-limit-down", limit))
+                .await?;
+            format!("Download limit: {}", speed_label(limit))
+        }
+        ServerAction::UploadLimit(limit) => {
+            rpc.call("session-set", limit_arguments("speed-limit-up", limit))
+                .await?;
+            format!("Upload limit: {}", speed_label(limit))
+        }
+    };
+
+    let title = config
+        .server_name(connection.url())
+        .unwrap_or_else(|| connection.url().to_string());
+    show_notification(app, &title, &message);
+    Ok(())
+}
+
+fn limit_arguments(field: &str, limit: Option<u64>) -> Value {
+    let mut arguments = json!({});
+    arguments[format!("{field}-enabled")] = limit.is_some().into();
+    if let Some(limit) = limit {
+        arguments[field] = limit.into();
+    }
+    arguments
+}
+
+// Every line of the clipboard that is a magnet link or a .torrent url is added
+async fn add_from_clipboard(app: &AppHandle, server: Option<&str>) -> Result<(), String> {
+    let text = app
+        .clipboard()
+        .read_text()
+        .map_err(|e| format!("Unable to read clipboard: {e}"))?;
+    let torrents: Vec<String> = text
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| l.starts_with("magnet:?") || is_torrent_url(l))
+        .map(|l| l.to_string())
+        .collect();
+    if torrents.is_empty() {
+        return Err("Clipboard does not contain magnet links or torrent urls".to_string());
+    }
+
+    add_without_dialog(
+        app,
+        &AddArgs {
+            torrents,
+            server: server.map(|s| s.to_string()),
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn action_ids_round_trip() {
+        let actions = [
+            ServerAction::PauseAll,
+            ServerAction::ResumeAll,
+            ServerAction::ToggleAltSpeed,
+            ServerAction::AddFromClipboard,
+            ServerAction::DownloadLimit(None),
+            ServerAction::DownloadLimit(Some(1024)),
+            ServerAction::UploadLimit(None),
+            ServerAction::UploadLimit(Some(100)),
+        ];
+        for action in actions {
+            assert!(!action.id().contains(':'));
+            assert_eq!(ServerAction::from_id(&action.id()), Some(action));
+        }
+        assert_eq!(ServerAction::from_id("limitdown-x"), None);
+        assert_eq!(ServerAction::from_id("open"), None);
+    }
+}
@@ -0,0 +1,410 @@
+// TrguiNG - next gen remote GUI for transmission torrent daemon
+// Copyright (C) 2023  qu1ck (mail at qu1ck.org)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use base64::{engine::general_purpose::STANDARD as b64engine, Engine as _};
+use serde::Deserialize;
+use tauri::{AppHandle, Manager};
+
+use crate::httpclient::ClientSettings;
+use crate::poller::{Connection, PollerConfig};
+use crate::rss::RssConfig;
+use crate::watch::WatchFolder;
+
+// Written by the frontend, only the parts needed without a window are read here
+const CONFIG_FILE: &str = "trguing.json";
+
+// Same as obfuscate/deobfuscate in config.ts, server passwords are stored this way
+const BASE64_CHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/=";
+const ROTATION_CHARS: &str = "HfxgnvteJy86p0ZPDdIYmc5r[kOj9EowACSWG7is%VNBXMhKFa2UlL31bzu4RQTq:";
+const OBFUSCATION_PREFIX: &str = "TrguiNG:";
+
+// Values that were not obfuscated are returned as is
+fn deobfuscate(s: &str) -> String {
+    let b64: Option<String> = s
+        .chars()
+        .map(|c| {
+            ROTATION_CHARS
+                .find(c)
+                .map(|i| BASE64_CHARS.as_bytes()[i] as char)
+        })
+        .collect();
+    b64.and_then(|b64| b64engine.decode(b64).ok())
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .and_then(|d| d.strip_prefix(OBFUSCATION_PREFIX).map(|p| p.to_string()))
+        .unwrap_or_else(|| s.to_string())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Intervals {
+    torrents_minimized: u64,
+}
+
+impl Default for Intervals {
+    fn default() -> Self {
+        Self {
+            torrents_minimized: 60,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PathMapping {
+    from: String,
+    to: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerConfig {
+    name: String,
+    connection: Connection,
+    #[serde(default)]
+    path_mappings: Vec<PathMapping>,
+    #[serde(default)]
+    intervals: Intervals,
+}
+
+// Cookie and extra headers for downloading torrent files from a site and its subdomains
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct DownloadSite {
+    host: String,
+    cookie: String,
+    // "Name: value" lines
+    headers: Vec<String>,
+}
+
+impl DownloadSite {
+    fn matches(&self, host: &str) -> bool {
+        let site_host = self.host.trim().to_lowercase();
+        !site_host.is_empty() && (host == site_host || host.ends_with(&format!(".{site_host}")))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct AppSettings {
+    open_tabs: Vec<String>,
+    // -1 when no tab is active
+    last_tab: i64,
+    delete_added: bool,
+    toast_notifications: bool,
+    toast_notification_sound: bool,
+    show_tray_icon: bool,
+    watch_folders: Vec<WatchFolder>,
+    clipboard_monitoring: bool,
+    download_sites: Vec<DownloadSite>,
+    rss: RssConfig,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            open_tabs: vec![],
+            last_tab: 0,
+            delete_added: false,
+            toast_notifications: true,
+            toast_notification_sound: true,
+            show_tray_icon: true,
+            watch_folders: vec![],
+            clipboard_monitoring: false,
+            download_sites: vec![],
+            rss: RssConfig::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct AppConfig {
+    servers: Vec<ServerConfig>,
+    app: AppSettings,
+}
+
+impl AppConfig {
+    pub fn load(app: &AppHandle) -> Result<Self, String> {
+        let path = app
+            .path()
+            .config_dir()
+            .map_err(|e| format!("Unable to resolve config dir: {e}"))?
+            .join(CONFIG_FILE);
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("Unable to read {}: {e}", path.display())),
+        };
+        Self::parse(&text).map_err(|e| format!("Unable to parse {}: {e}", path.display()))
+    }
+
+    fn parse(text: &str) -> Result<Self, serde_json::Error> {
+        let mut config: Self = serde_json::from_str(text)?;
+        for server in config.servers.iter_mut() {
+            server.connection.map_passwords(deobfuscate);
+        }
+        Ok(config)
+    }
+
+    // Same servers and intervals that the frontend polls with when its window is closed
+    pub fn poller_configs(&self) -> Vec<PollerConfig> {
+        self.servers
+            .iter()
+            .filter(|s| self.app.open_tabs.contains(&s.name))
+            .map(|s| {
+                PollerConfig::new(
+                    s.name.clone(),
+                    s.connection.clone(),
+                    s.intervals.torrents_minimized.max(1),
+                )
+            })
+            .collect()
+    }
+
+    // Connection of the named server or of the last active tab when no name is given
+    pub fn server_connection(&self, name: Option<&str>) -> Option<Connection> {
+        let name = match name {
+            Some(name) => name,
+            None => self
+                .app
+                .open_tabs
+                .get(usize::try_from(self.app.last_tab).unwrap_or(usize::MAX))
+                .or_else(|| self.app.open_tabs.first())
+                .or_else(|| self.servers.first().map(|s| &s.name))?,
+        };
+        self.servers
+            .iter()
+            .find(|s| s.name == name)
+            .map(|s| s.connection.clone())
+    }
+
+    pub fn client_settings(&self) -> Vec<ClientSettings> {
+        self.servers
+            .iter()
+            .flat_map(|s| s.connection.client_settings())
+            .collect()
+    }
+
+    // Pairs of server url and name
+    pub fn server_names(&self) -> Vec<(String, String)> {
+        self.servers
+            .iter()
+            .map(|s| (s.connection.url().to_string(), s.name.clone()))
+            .collect()
+    }
+
+    pub fn server_name(&self, url: &str) -> Option<String> {
+        self.servers
+            .iter()
+            .find(|s| s.connection.url() == url)
+            .map(|s| s.name.clone())
+    }
+
+    // Maps a path on the server at the url to a local one, same as pathMapFromServer
+    // in the frontend.
+    pub fn map_path_from_server(&self, url: &str, path: &str) -> String {
+        let normalize = |p: &str| p.replace('\\', "/");
+        let normalized_path = normalize(path);
+        self.servers
+            .iter()
+            .find(|s| s.connection.url() == url)
+            .and_then(|s| {
+                s.path_mappings.iter().find(|m| {
+                    !m.from.is_empty() && normalized_path.starts_with(&normalize(&m.from))
+                })
+            })
+            .map(|m| format!("{}{}", m.to, &path[m.from.len()..]))
+            .unwrap_or_else(|| path.to_string())
+    }
+
+    // Headers to send when downloading a torrent file from the url
+    pub fn site_headers(&self, url: &str) -> Vec<(String, String)> {
+        let Some(host) = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(|h| h.to_lowercase()))
+        else {
+            return vec![];
+        };
+
+        let mut headers = vec![];
+        for site in self.app.download_sites.iter().filter(|s| s.matches(&host)) {
+            if !site.cookie.trim().is_empty() {
+                headers.push(("Cookie".to_string(), site.cookie.trim().to_string()));
+            }
+            headers.extend(site.headers.iter().filter_map(|h| {
+                h.split_once(':')
+                    .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            }));
+        }
+        headers
+    }
+
+    pub fn watch_folders(&self) -> Vec<WatchFolder> {
+        self.app.watch_folders.clone()
+    }
+
+    pub fn rss(&self) -> RssConfig {
+        self.app.rss.clone()
+    }
+
+    // Copied links are added from the tray menu, so monitoring needs the tray icon
+    pub fn clipboard_monitoring(&self) -> bool {
+        self.app.clipboard_monitoring && self.app.show_tray_icon
+    }
+
+    pub fn delete_added(&self) -> bool {
+        self.app.delete_added
+    }
+
+    pub fn toast(&self) -> bool {
+        self.app.toast_notifications
+    }
+
+    pub fn sound(&self) -> bool {
+        self.app.toast_notification_sound
+    }
+
+    pub fn show_tray_icon(&self) -> bool {
+        self.app.show_tray_icon
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deobfuscates_frontend_passwords() {
+        // Written by obfuscate() in config.ts
+        assert_eq!(deobfuscate("ceysE57ZdUV%E5zlkrJ2"), "hunter2");
+        assert_eq!(
+            deobfuscate("ceysE57ZdUVFFudU91wgMsyGJZ8xBH::"),
+            "pässwörd €"
+        );
+        assert_eq!(deobfuscate("ceysE57ZdU%:"), "");
+    }
+
+    #[test]
+    fn keeps_plain_passwords() {
+        assert_eq!(deobfuscate("hunter2"), "hunter2");
+        assert_eq!(deobfuscate("with space"), "with space");
+        assert_eq!(deobfuscate(""), "");
+    }
+
+    #[test]
+    fn parses_frontend_config() {
+        let config = AppConfig::parse(
+            r#"{
+                "servers": [{
+                    "name": "home",
+                    "connection": {
+                        "url": "http://localhost:9091/transmission/rpc",
+                        "username": "user",
+                        "password": "ceysE57ZdUV%E5zlkrJ2"
+                    }
+                }],
+                "app": { "openTabs": ["home"], "lastTab": -1 }
+            }"#,
+        )
+        .unwrap();
+        let connection = config.server_connection(None).unwrap();
+        assert_eq!(connection.password(), "hunter2");
+    }
+
+    #[test]
+    fn deobfuscates_connection_secrets() {
+        let config = AppConfig::parse(
+            r#"{
+                "servers": [{
+                    "name": "home",
+                    "connection": {
+                        "url": "https://localhost:9091/transmission/rpc",
+                        "username": "user",
+                        "password": "ceysE57ZdUV%E5zlkrJ2",
+                        "proxy": { "type": "socks5", "host": "proxy", "port": 1080,
+                            "username": "user", "password": "ceysE57ZdUV%E5zlkrJ2" },
+                        "tls": { "clientCert": "client.p12", "clientCertPassword": "ceysE57ZdUV%E5zlkrJ2" }
+                    }
+                }]
+            }"#,
+        )
+        .unwrap();
+        let expected: ClientSettings = serde_json::from_str(
+            r#"{
+                "proxy": { "type": "socks5", "host": "proxy", "port": 1080,
+                    "username": "user", "password": "hunter2" },
+                "tls": { "clientCert": "client.p12", "clientCertPassword": "hunter2" }
+            }"#,
+        )
+        .unwrap();
+        let connection = config.server_connection(Some("home")).unwrap();
+        assert_eq!(connection.password(), "hunter2");
+        assert_eq!(connection.client_settings()[0], expected);
+    }
+
+    #[test]
+    fn monitors_clipboard_only_with_tray_icon() {
+        let config = |app: &str| AppConfig::parse(&format!(r#"{{"app": {app}}}"#)).unwrap();
+        assert!(config(r#"{ "clipboardMonitoring": true }"#).clipboard_monitoring());
+        assert!(
+            !config(r#"{ "clipboardMonitoring": true, "showTrayIcon": false }"#)
+                .clipboard_monitoring()
+        );
+        assert!(!config(r#"{ "clipboardMonitoring": false }"#).clipboard_monitoring());
+    }
+
+    #[test]
+    fn matches_download_sites() {
+        let site = |host: &str| DownloadSite {
+            host: host.to_string(),
+            ..Default::default()
+        };
+        assert!(site("example.com").matches("example.com"));
+        assert!(site(" Example.COM ").matches("example.com"));
+        assert!(site("example.com").matches("tracker.example.com"));
+        assert!(!site("example.com").matches("badexample.com"));
+        assert!(!site("tracker.example.com").matches("example.com"));
+        assert!(!site("").matches("example.com"));
+        assert!(!site("  ").matches(""));
+    }
+
+    #[test]
+    fn finds_site_headers() {
+        let config = AppConfig::parse(
+            r#"{
+                "app": {
+                    "downloadSites": [{
+                        "host": "example.com",
+                        "cookie": " uid=1; pass=abc ",
+                        "headers": ["Referer: https://example.com/", "invalid"]
+                    }]
+                }
+            }"#,
+        )
+        .unwrap();
+        let expected = vec![
+            ("Cookie".to_string(), "uid=1; pass=abc".to_string()),
+            ("Referer".to_string(), "https://example.com/".to_string()),
+        ];
+        assert_eq!(
+            config.site_headers("https://DL.Example.com/file.torrent"),
+            expected
+        );
+        assert!(config
+            .site_headers("https://other.org/file.torrent")
+            .is_empty());
+        assert!(config.site_headers("not a url").is_empty());
+    }
+}
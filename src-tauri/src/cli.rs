@@ -0,0 +1,485 @@
+// TrguiNG - next gen remote GUI for transmission torrent daemon
+// Copyright (C) 2023  qu1ck (mail at qu1ck.org)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use serde_json::{json, Value};
+use tauri::{async_runtime, AppHandle, Manager};
+use tauri_plugin_cli::{Matches, SubcommandMatches};
+
+use crate::appconfig::AppConfig;
+use crate::httpclient::HttpClients;
+use crate::rpc::{AddOptions, Rpc};
+use crate::session::TransmissionSessions;
+use crate::util::human_size;
+
+const TORRENT_FIELDS: [&str; 13] = [
+    "id",
+    "hashString",
+    "name",
+    "status",
+    "percentDone",
+    "totalSize",
+    "rateDownload",
+    "rateUpload",
+    "eta",
+    "downloadDir",
+    "labels",
+    "error",
+    "errorString",
+];
+
+pub fn flag(matches: &Matches, name: &str) -> bool {
+    matches
+        .args
+        .get(name)
+        .is_some_and(|arg| arg.value.as_bool() == Some(true))
+}
+
+pub fn value(matches: &Matches, name: &str) -> Option<String> {
+    matches
+        .args
+        .get(name)
+        .and_then(|arg| arg.value.as_str())
+        .map(|v| v.to_string())
+}
+
+pub fn values(matches: &Matches, name: &str) -> Vec<String> {
+    matches
+        .args
+        .get(name)
+        .and_then(|arg| arg.value.as_array())
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(|v| v.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Options shared by the main command and the add subcommand
+pub fn add_options(matches: &Matches) -> AddOptions {
+    AddOptions {
+        download_dir: value(matches, "download-dir"),
+        labels: value(matches, "labels")
+            .map(|labels| {
+                labels
+                    .split(',')
+                    .map(|l| l.trim().to_string())
+                    .filter(|l| !l.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        paused: flag(matches, "paused").then_some(true),
+        priority: value(matches, "priority").and_then(|p| match p.as_str() {
+            "low" => Some(-1),
+            "normal" => Some(0),
+            "high" => Some(1),
+            _ => None,
+        }),
+    }
+}
+
+// Release builds on Windows use the gui subsystem and start without a console,
+// output goes to the console of the shell that started the app if there is one.
+#[cfg(target_os = "windows")]
+pub fn attach_console() {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    // Fails harmlessly when the parent has no console, redirected output is kept
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn attach_console() {}
+
+// Runs a subcommand against a configured server without starting the gui,
+// returns process exit code.
+pub fn run(app: &AppHandle, subcommand: &SubcommandMatches) -> i32 {
+    attach_console();
+    match async_runtime::block_on(run_subcommand(app, subcommand)) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{e}");
+            1
+        }
+    }
+}
+
+async fn run_subcommand(app: &AppHandle, subcommand: &SubcommandMatches) -> Result<(), String> {
+    let matches = &subcommand.matches;
+    let config = AppConfig::load(app)?;
+    let server = value(matches, "server");
+    let connection = config
+        .server_connection(server.as_deref())
+        .ok_or_else(|| format!("Server {:?} not found", server.unwrap_or_default()))?;
+    let clients = app.state::<HttpClients>();
+    let sessions = app.state::<TransmissionSessions>();
+    let rpc = Rpc::new(&clients, &sessions, &connection).with_config(&config);
+    let json = flag(matches, "json");
+
+    match subcommand.name.as_str() {
+        "list" => list(&rpc, json).await,
+        "start" => rpc
+            .call("torrent-start", ids_argument(matches, true)?)
+            .await
+            .map(|_| ()),
+        "stop" => rpc
+            .call("torrent-stop", ids_argument(matches, true)?)
+            .await
+            .map(|_| ()),
+        "remove" => {
+            let mut arguments = ids_argument(matches, false)?;
+            arguments["delete-local-data"] = flag(matches, "delete-data").into();
+            rpc.call("torrent-remove", arguments).await.map(|_| ())
+        }
+        "add" => {
+            add(
+                &rpc,
+                &values(matches, "torrent"),
+                &add_options(matches),
+                json,
+            )
+            .await
+        }
+        "stats" => stats(&rpc, json).await,
+        name => Err(format!("Unknown command {name}")),
+    }
+}
+
+// Ids may be numeric torrent ids or info hashes, "all" selects every torrent
+fn ids_argument(matches: &Matches, allow_all: bool) -> Result<Value, String> {
+    let ids = values(matches, "ids");
+    if ids.iter().any(|id| id == "all") {
+        if allow_all {
+            return Ok(json!({}));
+        }
+        return Err("Torrents must be listed explicitly".to_string());
+    }
+
+    let ids: Vec<Value> = ids
+        .iter()
+        .map(|id| {
+            id.parse::<i64>()
+                .map(Value::from)
+                .unwrap_or_else(|_| id.as_str().into())
+        })
+        .collect();
+    Ok(json!({ "ids": ids }))
+}
+
+async fn list(rpc: &Rpc<'_>, json: bool) -> Result<(), String> {
+    let result = rpc
+        .call("torrent-get", json!({ "fields": TORRENT_FIELDS }))
+        .await?;
+    let torrents = result
+        .get("torrents")
+        .and_then(|t| t.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&torrents).unwrap());
+        return Ok(());
+    }
+
+    let rows: Vec<Vec<String>> = torrents
+        .iter()
+        .map(|t| {
+            vec![
+                t["id"].to_string(),
+                format!("{:.1}%", t["percentDone"].as_f64().unwrap_or(0.0) * 100.0),
+                human_size(t["totalSize"].as_i64().unwrap_or(0)),
+                format!("{}/s", human_size(t["rateDownload"].as_i64().unwrap_or(0))),
+                format!("{}/s", human_size(t["rateUpload"].as_i64().unwrap_or(0))),
+                status_text(t).to_string(),
+                t["name"].as_str().unwrap_or("").to_string(),
+            ]
+        })
+        .collect();
+    print_table(
+        &["ID", "Done", "Size", "Down", "Up", "Status", "Name"],
+        &rows,
+    );
+
+    Ok(())
+}
+
+async fn add(
+    rpc: &Rpc<'_>,
+    torrents: &[String],
+    options: &AddOptions,
+    json: bool,
+) -> Result<(), String> {
+    let mut results = vec![];
+    let mut failed = 0;
+    for torrent in torrents {
+        match rpc.add_torrent(torrent, options).await {
+            Ok(result) => {
+                if !json {
+                    let status = if result.duplicate {
+                        "Already exists"
+                    } else {
+                        "Added"
+                    };
+                    println!("{status}: {}", result.name);
+                }
+                results.push(json!({
+                    "torrent": torrent,
+                    "name": result.name,
+                    "duplicate": result.duplicate,
+                }));
+            }
+            Err(e) => {
+                failed += 1;
+                if !json {
+                    println!("Failed: {torrent}: {e}");
+                }
+                results.push(json!({ "torrent": torrent, "error": e }));
+            }
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&results).unwrap());
+    }
+    if failed > 0 {
+        return Err(format!(
+            "{failed} of {} torrents were not added",
+            torrents.len()
+        ));
+    }
+    Ok(())
+}
+
+async fn stats(rpc: &Rpc<'_>, json: bool) -> Result<(), String> {
+    let stats = rpc.call("session-stats", json!({})).await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats).unwrap());
+        return Ok(());
+    }
+
+    let bytes = |v: &Value| human_size(v.as_i64().unwrap_or(0));
+    let rows = vec![
+        vec![
+            "Torrents".to_string(),
+            format!(
+                "{} (active {}, paused {})",
+                stats["torrentCount"], stats["activeTorrentCount"], stats["pausedTorrentCount"]
+            ),
+        ],
+        vec![
+            "Download speed".to_string(),
+            format!("{}/s", bytes(&stats["downloadSpeed"])),
+        ],
+        vec![
+            "Upload speed".to_string(),
+            format!("{}/s", bytes(&stats["uploadSpeed"])),
+        ],
+        vec![
+            "Downloaded (session)".to_string(),
+            bytes(&stats["current-stats"]["downloadedBytes"]),
+        ],
+        vec![
+            "Uploaded (session)".to_string(),
+            bytes(&stats["current-stats"]["uploadedBytes"]),
+        ],
+        vec![
+            "Downloaded (total)".to_string(),
+            bytes(&stats["cumulative-stats"]["downloadedBytes"]),
+        ],
+        vec![
+            "Uploaded (total)".to_string(),
+            bytes(&stats["cumulative-stats"]["uploadedBytes"]),
+        ],
+    ];
+    print_table(&[], &rows);
+
+    Ok(())
+}
+
+fn status_text(torrent: &Value) -> &'static str {
+    if torrent["error"].as_i64().unwrap_or(0) != 0 {
+        return "Error";
+    }
+    match torrent["status"].as_i64().unwrap_or(-1) {
+        0 => "Stopped",
+        1 => "Queued to verify",
+        2 => "Verifying",
+        3 => "Queued to download",
+        4 => "Downloading",
+        5 => "Queued to seed",
+        6 => "Seeding",
+        _ => "Unknown",
+    }
+}
+
+fn print_table(header: &[&str], rows: &[Vec<String>]) {
+    for line in format_table(header, rows) {
+        println!("{line}");
+    }
+}
+
+// Left aligned columns, the last one is not padded
+fn format_table(header: &[&str], rows: &[Vec<String>]) -> Vec<String> {
+    let columns = rows
+        .iter()
+        .map(|r| r.len())
+        .max()
+        .unwrap_or(0)
+        .max(header.len());
+    let mut widths = vec![0; columns];
+    for (i, h) in header.iter().enumerate() {
+        widths[i] = h.chars().count();
+    }
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(cell.chars().count());
+        }
+    }
+
+    let format_row = |cells: Vec<&str>| {
+        let last = cells.len().saturating_sub(1);
+        let line: Vec<String> = cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                if i == last {
+                    cell.to_string()
+                } else {
+                    format!("{cell:<width$}", width = widths[i])
+                }
+            })
+            .collect();
+        line.join("  ")
+    };
+
+    let mut lines = vec![];
+    if !header.is_empty() {
+        lines.push(format_row(header.to_vec()));
+    }
+    for row in rows {
+        lines.push(format_row(row.iter().map(|c| c.as_str()).collect()));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(args: &[(&str, Value)]) -> Matches {
+        let mut matches = Matches::default();
+        for (name, value) in args {
+            let arg = matches.args.entry(name.to_string()).or_default();
+            arg.value = value.clone();
+            arg.occurrences = 1;
+        }
+        matches
+    }
+
+    #[test]
+    fn reads_arguments() {
+        let m = matches(&[
+            ("json", json!(true)),
+            ("paused", json!(false)),
+            ("server", json!("home")),
+            ("ids", json!(["1", "2"])),
+        ]);
+        assert!(flag(&m, "json"));
+        assert!(!flag(&m, "paused"));
+        assert!(!flag(&m, "server"));
+        assert!(!flag(&m, "missing"));
+        assert_eq!(value(&m, "server").as_deref(), Some("home"));
+        assert_eq!(value(&m, "json"), None);
+        assert_eq!(values(&m, "ids"), ["1", "2"]);
+        assert!(values(&m, "server").is_empty());
+    }
+
+    #[test]
+    fn parses_ids_and_hashes() {
+        let hash = "c12fe1c06bba254a9dc9f519b335aa7c1367a88a";
+        let m = matches(&[("ids", json!(["1", hash, "42"]))]);
+        assert_eq!(
+            ids_argument(&m, false).unwrap(),
+            json!({ "ids": [1, hash, 42] })
+        );
+
+        let all = matches(&[("ids", json!(["1", "all"]))]);
+        assert_eq!(ids_argument(&all, true).unwrap(), json!({}));
+        assert!(ids_argument(&all, false).is_err());
+    }
+
+    #[test]
+    fn maps_add_options() {
+        let options = add_options(&matches(&[
+            ("download-dir", json!("/data")),
+            ("labels", json!(" tv, hd,,")),
+            ("paused", json!(true)),
+            ("priority", json!("high")),
+        ]));
+        assert_eq!(options.download_dir.as_deref(), Some("/data"));
+        assert_eq!(options.labels, ["tv", "hd"]);
+        assert_eq!(options.paused, Some(true));
+        assert_eq!(options.priority, Some(1));
+
+        let priority = |p: &str| add_options(&matches(&[("priority", json!(p))])).priority;
+        assert_eq!(priority("low"), Some(-1));
+        assert_eq!(priority("normal"), Some(0));
+        assert_eq!(priority("urgent"), None);
+
+        let defaults = add_options(&Matches::default());
+        assert_eq!(defaults.download_dir, None);
+        assert!(defaults.labels.is_empty());
+        assert_eq!(defaults.paused, None);
+        assert_eq!(defaults.priority, None);
+    }
+
+    #[test]
+    fn maps_status() {
+        assert_eq!(status_text(&json!({ "status": 4 })), "Downloading");
+        assert_eq!(status_text(&json!({ "status": 6 })), "Seeding");
+        assert_eq!(status_text(&json!({ "status": 6, "error": 2 })), "Error");
+        assert_eq!(status_text(&json!({})), "Unknown");
+    }
+
+    #[test]
+    fn aligns_table_columns() {
+        let rows = vec![
+            vec!["1".to_string(), "Seeding".to_string(), "Ubuntu".to_string()],
+            vec!["100".to_string(), "Ü".to_string(), "Debian".to_string()],
+        ];
+        assert_eq!(
+            format_table(&["ID", "Status", "Name"], &rows),
+            [
+                "ID   Status   Name",
+                "1    Seeding  Ubuntu",
+                "100  Ü        Debian",
+            ]
+        );
+        assert_eq!(
+            format_table(&[], &[vec!["Key".to_string(), "Value".to_string()]]),
+            ["Key  Value"]
+        );
+        assert!(format_table(&[], &[]).is_empty());
+    }
+}
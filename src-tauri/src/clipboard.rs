@@ -0,0 +1,309 @@
+// TrguiNG - next gen remote GUI for transmission torrent daemon
+// Copyright (C) 2023  qu1ck (mail at qu1ck.org)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::Mutex,
+    time::Duration,
+};
+
+use tauri::{
+    async_runtime::{self, JoinHandle},
+    AppHandle, Manager,
+};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::ipc::{add_without_dialog, show_notification, AddArgs};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+// Number of offered links that are remembered to not offer them again
+const SEEN_SIZE: usize = 200;
+
+#[derive(Clone)]
+pub struct CopiedTorrent {
+    pub name: String,
+    pub link: String,
+}
+
+#[derive(Default)]
+struct SeenLinks {
+    keys: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl SeenLinks {
+    // Returns false if the key was already seen
+    fn insert(&mut self, key: String) -> bool {
+        if !self.keys.insert(key.clone()) {
+            return false;
+        }
+        self.order.push_back(key);
+        if self.order.len() > SEEN_SIZE {
+            if let Some(oldest) = self.order.pop_front() {
+                self.keys.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+#[derive(Default)]
+struct ClipboardWatch {
+    task: Option<JoinHandle<()>>,
+    seen: SeenLinks,
+    // Links offered in the last notification, added from the tray menu
+    pending: Vec<CopiedTorrent>,
+}
+
+#[derive(Default)]
+pub struct ClipboardWatchHandle(Mutex<ClipboardWatch>);
+
+pub fn set_enabled(app: &AppHandle, enabled: bool) {
+    let handle = app.state::<ClipboardWatchHandle>();
+    let mut watch = handle.0.lock().unwrap();
+    if enabled == watch.task.is_some() {
+        return;
+    }
+
+    match watch.task.take() {
+        Some(task) => {
+            task.abort();
+            let had_pending = !watch.pending.is_empty();
+            watch.pending.clear();
+            drop(watch);
+            if had_pending {
+                crate::tray::rebuild_menu(app);
+            }
+        }
+        None => watch.task = Some(async_runtime::spawn(watch_task(app.clone()))),
+    }
+}
+
+pub fn pending(app: &AppHandle) -> Vec<CopiedTorrent> {
+    app.state::<ClipboardWatchHandle>()
+        .0
+        .lock()
+        .unwrap()
+        .pending
+        .clone()
+}
+
+// Adds the offered links to the default server
+pub fn add_pending(app: &AppHandle) {
+    let torrents: Vec<String> = {
+        let handle = app.state::<ClipboardWatchHandle>();
+        let mut watch = handle.0.lock().unwrap();
+        watch.pending.drain(..).map(|t| t.link).collect()
+    };
+    crate::tray::rebuild_menu(app);
+    if torrents.is_empty() {
+        return;
+    }
+
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        let args = AddArgs {
+            torrents,
+            ..Default::default()
+        };
+        if let Err(e) = add_without_dialog(&app, &args).await {
+            show_notification(&app, "Unable to add torrent", &e);
+        }
+    });
+}
+
+async fn watch_task(app: AppHandle) {
+    // Whatever is in the clipboard when monitoring starts is not offered
+    let mut last_text = app.clipboard().read_text().unwrap_or_default();
+
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let Ok(text) = app.clipboard().read_text() else {
+            continue;
+        };
+        if text == last_text {
+            continue;
+        }
+        last_text = text;
+
+        let copied: Vec<CopiedTorrent> = {
+            let handle = app.state::<ClipboardWatchHandle>();
+            let mut watch = handle.0.lock().unwrap();
+            let copied: Vec<CopiedTorrent> = find_torrents(&last_text)
+                .into_iter()
+                .filter(|(key, _)| watch.seen.insert(key.clone()))
+                .map(|(_, torrent)| torrent)
+                .collect();
+            if !copied.is_empty() {
+                watch.pending = copied.clone();
+            }
+            copied
+        };
+        if copied.is_empty() {
+            continue;
+        }
+
+        crate::tray::rebuild_menu(&app);
+        // Desktop notifications can't have action buttons, the tray menu item
+        // added for the pending links serves as the action.
+        let names: Vec<&str> = copied.iter().map(|t| t.name.as_str()).collect();
+        let title = if copied.len() == 1 {
+            "Torrent link copied".to_string()
+        } else {
+            format!("{} torrent links copied", copied.len())
+        };
+        show_notification(
+            &app,
+            &title,
+            &format!(
+                "{}\nUse \"{}\" in the tray menu to add to the default server",
+                names.join("\n"),
+                pending_item_text(&copied)
+            ),
+        );
+    }
+}
+
+pub fn pending_item_text(pending: &[CopiedTorrent]) -> String {
+    match pending {
+        [torrent] => format!("Add copied: {}", torrent.name),
+        _ => format!("Add {} copied links", pending.len()),
+    }
+}
+
+// Magnet links and urls of .torrent files in the text, with the key that
+// identifies them, which is the info hash for magnet links.
+fn find_torrents(text: &str) -> Vec<(String, CopiedTorrent)> {
+    text.lines()
+        .map(|l| l.trim())
+        .filter_map(|link| {
+            let (key, name) = if link.starts_with("magnet:?") {
+                let key = magnet_param(link, "xt")
+                    .and_then(|xt| xt.strip_prefix("urn:btih:").map(normalize_btih))
+                    .unwrap_or_else(|| link.to_string());
+                let name = magnet_param(link, "dn").unwrap_or_else(|| key.clone());
+                (key, name)
+            } else if is_torrent_url(link) {
+                (link.to_string(), link.to_string())
+            } else {
+                return None;
+            };
+            Some((
+                key,
+                CopiedTorrent {
+                    name,
+                    link: link.to_string(),
+                },
+            ))
+        })
+        .collect()
+}
+
+fn magnet_param(link: &str, name: &str) -> Option<String> {
+    link["magnet:?".len()..]
+        .split('&')
+        .filter_map(|p| p.split_once('='))
+        .find(|(k, _)| *k == name)
+        .and_then(|(_, v)| {
+            urlencoding::decode(&v.replace('+', " "))
+                .ok()
+                .map(|v| v.into_owned())
+        })
+}
+
+// Info hashes in magnet links are either 40 hex or 32 base32 characters,
+// both are converted to lowercase hex so that the same torrent has one key.
+fn normalize_btih(hash: &str) -> String {
+    if hash.len() != 32 {
+        return hash.to_lowercase();
+    }
+    let mut bytes = Vec::with_capacity(20);
+    let mut buffer: u64 = 0;
+    let mut bits = 0;
+    for c in hash.chars() {
+        let value = match c.to_ascii_uppercase() {
+            c @ 'A'..='Z' => c as u64 - 'A' as u64,
+            c @ '2'..='7' => c as u64 - '2' as u64 + 26,
+            _ => return hash.to_lowercase(),
+        };
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub(crate) fn is_torrent_url(link: &str) -> bool {
+    (link.starts_with("http://") || link.starts_with("https://"))
+        && !link.contains(char::is_whitespace)
+        && link
+            .split(['?', '#'])
+            .next()
+            .is_some_and(|path| path.to_lowercase().ends_with(".torrent"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEX_HASH: &str = "c12fe1c06bba254a9dc9f519b335aa7c1367a88a";
+    const BASE32_HASH: &str = "YEX6DQDLXISUVHOJ6UM3GNNKPQJWPKEK";
+
+    #[test]
+    fn normalizes_btih() {
+        assert_eq!(normalize_btih(HEX_HASH), HEX_HASH);
+        assert_eq!(normalize_btih(&HEX_HASH.to_uppercase()), HEX_HASH);
+        assert_eq!(normalize_btih(BASE32_HASH), HEX_HASH);
+        assert_eq!(normalize_btih(&BASE32_HASH.to_lowercase()), HEX_HASH);
+        // Not valid base32, kept as is
+        assert_eq!(
+            normalize_btih("0123456789ABCDEF0123456789ABCDEF"),
+            "0123456789abcdef0123456789abcdef"
+        );
+    }
+
+    #[test]
+    fn dedups_hex_and_base32_magnets() {
+        let text = format!(
+            "magnet:?xt=urn:btih:{HEX_HASH}&dn=Some+name\nmagnet:?xt=urn:btih:{BASE32_HASH}\n"
+        );
+        let found = find_torrents(&text);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].0, HEX_HASH);
+        assert_eq!(found[1].0, HEX_HASH);
+        assert_eq!(found[0].1.name, "Some name");
+
+        let mut seen = SeenLinks::default();
+        let new: Vec<_> = found
+            .into_iter()
+            .filter(|(key, _)| seen.insert(key.clone()))
+            .collect();
+        assert_eq!(new.len(), 1);
+    }
+
+    #[test]
+    fn finds_torrent_urls() {
+        let found = find_torrents(
+            "https://example.com/a.torrent?x=1\nhttps://example.com/page\nnot a link",
+        );
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, "https://example.com/a.torrent?x=1");
+    }
+}
@@ -14,17 +14,25 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::fs;
+use std::{fs, path::Path};
 
 use base64::{engine::general_purpose::STANDARD as b64engine, Engine as _};
 use font_loader::system_fonts;
 use lava_torrent::torrent::v1::Torrent;
-use tauri::{Emitter, EventTarget, State};
+use tauri::{async_runtime, Emitter, EventTarget, Manager, State};
 
 use crate::{
+    appconfig::AppConfig,
+    clipboard,
     createtorrent::{CreateCheckResult, CreationRequestsHandle, TorrentCreateInfo},
+    geoip::{DbInfo, GeoIpConfig},
+    httpclient::{read_limited, ClientSettings, HttpClients},
     poller::PollerConfig,
-    tray, PollerHandle,
+    rss::{self, FeedTestItem, HistoryEntry, RssConfig, RssFeed, RssRule},
+    torrentcache::TorrentCacheHandle,
+    tray,
+    watch::{self, WatchFolder},
+    PollerHandle,
 };
 
 #[derive(serde::Serialize)]
@@ -43,38 +51,87 @@ pub struct TorrentFileEntry {
 #[serde(rename_all = "camelCase")]
 pub struct TorrentReadResult {
     torrent_path: String,
-    metadata: String,
-    name: String,
+    pub metadata: String,
+    pub name: String,
     length: i64,
-    hash: String,
+    pub hash: String,
     files: Option<Vec<TorrentFileEntry>>,
     trackers: Vec<String>,
 }
 
+// Torrent files larger than this are rejected
+const MAX_TORRENT_SIZE: u64 = 10 * 1024 * 1024;
+
 #[tauri::command]
 pub async fn read_file(path: String) -> Result<TorrentReadResult, String> {
     let metadata = std::fs::metadata(path.clone());
     match metadata {
         Err(_) => return Err(format!("Failed to read file {path:?}")),
         Ok(metadata) => {
-            if metadata.len() > 10 * 1024 * 1024 {
+            if metadata.len() > MAX_TORRENT_SIZE {
                 return Err("File is too large".to_string());
             }
         }
     }
 
-    let read_result = tokio::fs::read(path.clone()).await;
-    if read_result.is_err() {
-        return Err(format!("Failed to read file {path:?}"));
+    match tokio::fs::read(path.clone()).await {
+        Err(_) => Err(format!("Failed to read file {path:?}")),
+        Ok(data) => parse_torrent(path, data),
+    }
+}
+
+// Downloads a .torrent file with the cookies and headers configured for the
+// site, so that links of private trackers work. Proxy and certificate settings
+// of the server the torrent is added to are used.
+#[tauri::command]
+pub async fn fetch_torrent(
+    app_handle: tauri::AppHandle,
+    url: String,
+    server: Option<String>,
+) -> Result<TorrentReadResult, String> {
+    let config = AppConfig::load(&app_handle)?;
+    let headers = config.site_headers(&url);
+    let settings = config
+        .server_connection(server.as_deref())
+        .map(|c| c.download_settings())
+        .unwrap_or_else(ClientSettings::download);
+    let clients = app_handle.state::<HttpClients>();
+    let mut result = download_torrent(&clients, &settings, &url, headers).await?;
+    // Not a local file, it must not be deleted after adding
+    result.torrent_path = String::new();
+    Ok(result)
+}
+
+pub async fn download_torrent(
+    clients: &HttpClients,
+    settings: &ClientSettings,
+    url: &str,
+    headers: Vec<(String, String)>,
+) -> Result<TorrentReadResult, String> {
+    let mut request = clients.get(settings)?.get(url);
+    for (name, value) in headers {
+        request = request.header(name, value);
     }
+    let response = request
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Unable to download {url}: {e}"))?;
+    let data = read_limited(response, MAX_TORRENT_SIZE)
+        .await
+        .map_err(|e| format!("Unable to download {url}: {e}"))?;
+
+    parse_torrent(url.to_string(), data)
+}
 
-    match Torrent::read_from_bytes(&read_result.as_ref().unwrap()[..]) {
+fn parse_torrent(path: String, data: Vec<u8>) -> Result<TorrentReadResult, String> {
+    match Torrent::read_from_bytes(&data[..]) {
         Err(e) => {
             println!("Failed to parse torrent {:?}", e.to_string());
             Err(format!("Failed to parse torrent {path:?}"))
         }
         Ok(torrent) => {
-            let b64 = b64engine.encode(read_result.unwrap());
+            let b64 = b64engine.encode(&data);
             let mut trackers = vec![];
 
             if let Some(announce_list) = torrent.announce_list.as_ref() {
@@ -108,11 +165,133 @@ pub async fn read_file(path: String) -> Result<TorrentReadResult, String> {
     }
 }
 
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateTorrent {
+    torrent_path: String,
+    name: String,
+    hash: String,
+    // False when the torrent is repeated within the batch
+    on_server: bool,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadError {
+    torrent_path: String,
+    error: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct BatchReadResult {
+    torrents: Vec<TorrentReadResult>,
+    duplicates: Vec<DuplicateTorrent>,
+    errors: Vec<ReadError>,
+}
+
+// Reads many torrent files at once so that they can be added in one step.
+// Torrents already on the server, according to the torrent cache, are not
+// returned with the new ones.
 #[tauri::command]
-pub async fn remove_file(path: String) {
-    if path.to_lowercase().ends_with(".torrent") && std::fs::remove_file(path.clone()).is_err() {
-        println!("Unable to remove file {path}");
+pub async fn read_files(
+    cache: State<'_, TorrentCacheHandle>,
+    paths: Vec<String>,
+    server_url: String,
+) -> Result<BatchReadResult, String> {
+    let mut seen = cache.hashes(&server_url).await;
+    let mut result = BatchReadResult {
+        torrents: vec![],
+        duplicates: vec![],
+        errors: vec![],
+    };
+
+    for path in paths {
+        match read_file(path.clone()).await {
+            Ok(torrent) => {
+                let hash = torrent.hash.to_lowercase();
+                if seen.insert(hash) {
+                    result.torrents.push(torrent);
+                } else {
+                    let on_server = !result.torrents.iter().any(|t| t.hash == torrent.hash);
+                    result.duplicates.push(DuplicateTorrent {
+                        torrent_path: torrent.torrent_path,
+                        name: torrent.name,
+                        hash: torrent.hash,
+                        on_server,
+                    });
+                }
+            }
+            Err(error) => result.errors.push(ReadError {
+                torrent_path: path,
+                error,
+            }),
+        }
     }
+
+    Ok(result)
+}
+
+// Removes an added .torrent or .magnet file, or moves it into the given directory
+#[tauri::command]
+pub async fn remove_file(path: String, move_to: Option<String>) -> Result<(), String> {
+    let lowercase = path.to_lowercase();
+    if !lowercase.ends_with(".torrent") && !lowercase.ends_with(".magnet") {
+        return Ok(());
+    }
+    async_runtime::spawn_blocking(move || match move_to {
+        None => {
+            std::fs::remove_file(&path).map_err(|e| format!("Unable to remove file {path}: {e}"))
+        }
+        Some(dir) => move_file(Path::new(&path), Path::new(&dir))
+            .map_err(|e| format!("Unable to move file {path} to {dir}: {e}")),
+    })
+    .await
+    .map_err(|e| format!("Unable to remove file: {e}"))?
+}
+
+fn move_file(path: &Path, dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let target = dir.join(path.file_name().unwrap_or_default());
+    // Rename does not work across file systems
+    if std::fs::rename(path, &target).is_err() {
+        std::fs::copy(path, &target)?;
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_clipboard_monitoring(app_handle: tauri::AppHandle, enabled: bool) {
+    clipboard::set_enabled(&app_handle, enabled);
+}
+
+#[tauri::command]
+pub async fn set_rss_config(app_handle: tauri::AppHandle, config: RssConfig) {
+    rss::set_config(&app_handle, config);
+}
+
+#[tauri::command]
+pub async fn test_rss_feed(
+    app_handle: tauri::AppHandle,
+    feed: RssFeed,
+    rules: Vec<RssRule>,
+) -> Result<Vec<FeedTestItem>, String> {
+    rss::test_feed(&app_handle, &feed, &rules).await
+}
+
+#[tauri::command]
+pub async fn rss_history(app_handle: tauri::AppHandle) -> Vec<HistoryEntry> {
+    rss::history(&app_handle)
+}
+
+#[tauri::command]
+pub async fn clear_rss_history(app_handle: tauri::AppHandle) -> Result<(), String> {
+    rss::clear_history(&app_handle)
+}
+
+#[tauri::command]
+pub async fn set_watch_folders(app_handle: tauri::AppHandle, folders: Vec<WatchFolder>) {
+    watch::set_folders(&app_handle, folders);
 }
 
 #[tauri::command]
@@ -154,6 +333,21 @@ pub async fn app_integration(mode: String) -> bool {
     crate::integrations::app_integration_impl(mode)
 }
 
+#[tauri::command]
+pub async fn set_geoip_config(app_handle: tauri::AppHandle, config: GeoIpConfig) {
+    crate::geoip::set_config(&app_handle, config).await;
+}
+
+#[tauri::command]
+pub async fn geoip_info(app_handle: tauri::AppHandle) -> Vec<DbInfo> {
+    crate::geoip::info(&app_handle).await
+}
+
+#[tauri::command]
+pub async fn update_geoip_db(app_handle: tauri::AppHandle, url: String) -> Result<(), String> {
+    crate::geoip::update_db(&app_handle, &url).await
+}
+
 #[tauri::command]
 pub async fn get_file_stats(path: String) -> FileStats {
     if let Ok(metadata) = std::path::PathBuf::from(path.clone()).metadata() {
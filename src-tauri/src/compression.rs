@@ -0,0 +1,183 @@
+// TrguiNG - next gen remote GUI for transmission torrent daemon
+// Copyright (C) 2023  qu1ck (mail at qu1ck.org)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::io;
+
+use async_compression::tokio::write::{BrotliEncoder, GzipEncoder, ZstdEncoder};
+use async_compression::Level;
+use hyper::body::Bytes;
+use hyper::header::ACCEPT_ENCODING;
+use hyper::HeaderMap;
+use tokio::io::AsyncWriteExt;
+
+// Responses smaller than this are not worth compressing
+pub const MIN_COMPRESS_SIZE: u64 = 1024;
+
+// Encodings supported for the webview hop, in order of preference
+const SUPPORTED_ENCODINGS: &[&str] = &["zstd", "br", "gzip"];
+
+// Streaming encoder for response bodies sent to the webview. Output is produced
+// as the input chunks are written, so that compressed responses can be streamed too.
+pub enum ContentEncoder {
+    Zstd(ZstdEncoder<Vec<u8>>),
+    // Boxed because its state is several kilobytes
+    Brotli(Box<BrotliEncoder<Vec<u8>>>),
+    Gzip(GzipEncoder<Vec<u8>>),
+}
+
+impl ContentEncoder {
+    // Picks the most preferred encoding allowed by the request's Accept-Encoding header.
+    // The hop to the webview is local so the fastest compression levels are used.
+    pub fn from_request(request_headers: &HeaderMap) -> Option<Self> {
+        let accepted: Vec<&str> = request_headers
+            .get_all(ACCEPT_ENCODING)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .filter_map(|e| {
+                let mut parts = e.split(';').map(|p| p.trim());
+                let name = parts.next()?;
+                let disallowed = parts.any(|p| {
+                    p.strip_prefix("q=")
+                        .and_then(|q| q.parse::<f32>().ok())
+                        .is_some_and(|q| q <= 0.0)
+                });
+                if disallowed {
+                    None
+                } else {
+                    Some(name)
+                }
+            })
+            .collect();
+
+        let encoding = SUPPORTED_ENCODINGS
+            .iter()
+            .find(|e| accepted.iter().any(|a| a.eq_ignore_ascii_case(e)))?;
+
+        match *encoding {
+            "zstd" => Some(Self::Zstd(ZstdEncoder::with_quality(
+                Vec::new(),
+                Level::Fastest,
+            ))),
+            "br" => Some(Self::Brotli(Box::new(BrotliEncoder::with_quality(
+                Vec::new(),
+                Level::Fastest,
+            )))),
+            "gzip" => Some(Self::Gzip(GzipEncoder::with_quality(
+                Vec::new(),
+                Level::Fastest,
+            ))),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Zstd(_) => "zstd",
+            Self::Brotli(_) => "br",
+            Self::Gzip(_) => "gzip",
+        }
+    }
+
+    // Compresses a chunk and returns whatever output the encoder produced so far,
+    // which may be empty.
+    pub async fn encode(&mut self, chunk: &[u8]) -> io::Result<Bytes> {
+        match self {
+            Self::Zstd(e) => {
+                e.write_all(chunk).await?;
+                Ok(std::mem::take(e.get_mut()).into())
+            }
+            Self::Brotli(e) => {
+                e.write_all(chunk).await?;
+                Ok(std::mem::take(e.get_mut()).into())
+            }
+            Self::Gzip(e) => {
+                e.write_all(chunk).await?;
+                Ok(std::mem::take(e.get_mut()).into())
+            }
+        }
+    }
+
+    // Finalizes the stream and returns the remaining output.
+    pub async fn finish(&mut self) -> io::Result<Bytes> {
+        match self {
+            Self::Zstd(e) => {
+                e.shutdown().await?;
+                Ok(std::mem::take(e.get_mut()).into())
+            }
+            Self::Brotli(e) => {
+                e.shutdown().await?;
+                Ok(std::mem::take(e.get_mut()).into())
+            }
+            Self::Gzip(e) => {
+                e.shutdown().await?;
+                Ok(std::mem::take(e.get_mut()).into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_compression::tokio::bufread::GzipDecoder;
+    use hyper::header::HeaderValue;
+    use tauri::async_runtime;
+    use tokio::io::AsyncReadExt;
+
+    fn negotiate(accept: &[&str]) -> Option<&'static str> {
+        let mut headers = HeaderMap::new();
+        for value in accept {
+            headers.append(ACCEPT_ENCODING, HeaderValue::from_str(value).unwrap());
+        }
+        ContentEncoder::from_request(&headers).map(|e| e.name())
+    }
+
+    #[test]
+    fn negotiates_encoding() {
+        assert_eq!(negotiate(&[]), None);
+        assert_eq!(negotiate(&["identity"]), None);
+        assert_eq!(negotiate(&["gzip, deflate, br, zstd"]), Some("zstd"));
+        assert_eq!(negotiate(&["gzip", "BR"]), Some("br"));
+        assert_eq!(negotiate(&["zstd;q=0, br;q=0.0, gzip;q=0.5"]), Some("gzip"));
+        assert_eq!(negotiate(&["zstd;q=0, br;q=0"]), None);
+    }
+
+    #[test]
+    fn encodes_stream() {
+        let input = "{\"torrents\": []}".repeat(1000);
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("gzip"));
+        let mut encoder = ContentEncoder::from_request(&headers).unwrap();
+
+        let output = async_runtime::block_on(async {
+            let mut output = vec![];
+            for chunk in input.as_bytes().chunks(100) {
+                output.extend_from_slice(&encoder.encode(chunk).await.unwrap());
+            }
+            output.extend_from_slice(&encoder.finish().await.unwrap());
+
+            let mut decoded = String::new();
+            GzipDecoder::new(&output[..])
+                .read_to_string(&mut decoded)
+                .await
+                .unwrap();
+            assert_eq!(decoded, input);
+            output
+        });
+        assert!(output.len() < input.len());
+    }
+}
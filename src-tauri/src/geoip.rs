@@ -14,13 +14,202 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::{net::IpAddr, sync::Arc};
+use std::{
+    collections::HashSet,
+    net::IpAddr,
+    num::NonZeroUsize,
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant, SystemTime},
+};
 
-use tauri::{AppHandle, Manager, State};
-use tokio::sync::Mutex;
+use async_compression::tokio::bufread::GzipDecoder;
+use lru::LruCache;
+use serde::Deserialize;
+use tauri::{
+    async_runtime::{self, JoinHandle},
+    AppHandle, Manager, State,
+};
+use tokio::io::AsyncReadExt;
+
+use crate::httpclient::{ClientSettings, HttpClients};
+use crate::util::write_atomic;
+
+type MmdbReader = maxminddb::Reader<Vec<u8>>;
+
+const COUNTRY_DB: &str = "dbip.mmdb";
+// Optional databases, looked up in the app data dir and then in resources
+const ASN_DB: &str = "dbip-asn.mmdb";
+const CITY_DB: &str = "dbip-city.mmdb";
+const DB_KINDS: [&str; 3] = ["country", "asn", "city"];
+
+const UPDATE_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const LOOKUP_CACHE_SIZE: usize = 4096;
+// Resolved database files are checked for changes at most this often
+const FILES_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GeoIpConfig {
+    // Custom database paths, default databases are used when empty
+    country_db: String,
+    asn_db: String,
+    city_db: String,
+    // Country database is downloaded from this url into the app data dir,
+    // updates are not used while a custom country database is set
+    update_url: String,
+    update_interval_days: u64,
+}
+
+// Databases are reopened when their path or modification time changes
+#[derive(Clone, PartialEq)]
+struct DbFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl DbFile {
+    fn new(path: PathBuf) -> Self {
+        let modified = path.metadata().and_then(|m| m.modified()).ok();
+        Self { path, modified }
+    }
+
+    fn open(&self) -> Option<MmdbReader> {
+        match maxminddb::Reader::open_readfile(&self.path) {
+            Ok(db) => Some(db),
+            Err(_) => {
+                println!("{} is invalid", self.path.display());
+                None
+            }
+        }
+    }
+}
+
+type DbFiles = [Option<DbFile>; 3];
+
+// Immutable set of opened databases that is shared by concurrent lookups.
+// It is replaced as a whole when any of the files changes, which also drops
+// the cached results.
+struct Databases {
+    files: DbFiles,
+    readers: [Option<MmdbReader>; 3],
+    cache: Mutex<LruCache<IpAddr, LookupResult>>,
+}
+
+impl Databases {
+    fn open(files: DbFiles) -> Self {
+        let readers = files.clone().map(|f| f.and_then(|f| f.open()));
+        Self {
+            files,
+            readers,
+            cache: Mutex::new(LruCache::new(NonZeroUsize::new(LOOKUP_CACHE_SIZE).unwrap())),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.readers.iter().all(|r| r.is_none())
+    }
+
+    fn lookup(&self, ip: IpAddr) -> LookupResult {
+        let [country_db, asn_db, city_db] = &self.readers;
+        let mut result = LookupResult::new(ip);
+
+        if let Some(country) = country_db.as_ref().and_then(|reader| {
+            reader
+                .lookup::<maxminddb::geoip2::Country>(ip)
+                .ok()
+                .flatten()
+                .and_then(|c| c.country)
+        }) {
+            result.iso_code = country.iso_code.map(|c| c.to_string());
+            result.name = country
+                .names
+                .and_then(|names| names.get("en").map(|s| s.to_string()));
+        }
+
+        if let Some(asn) = asn_db
+            .as_ref()
+            .and_then(|reader| reader.lookup::<maxminddb::geoip2::Asn>(ip).ok().flatten())
+        {
+            result.asn = asn.autonomous_system_number;
+            result.organization = asn.autonomous_system_organization.map(|o| o.to_string());
+        }
+
+        if let Some(city) = city_db
+            .as_ref()
+            .and_then(|reader| reader.lookup::<maxminddb::geoip2::City>(ip).ok().flatten())
+        {
+            result.city = city
+                .city
+                .and_then(|c| c.names)
+                .and_then(|names| names.get("en").map(|s| s.to_string()));
+            result.region = city
+                .subdivisions
+                .and_then(|s| s.into_iter().next())
+                .and_then(|s| s.names)
+                .and_then(|names| names.get("en").map(|s| s.to_string()));
+        }
+
+        result
+    }
+
+    fn lookup_all(&self, ips: Vec<IpAddr>) -> Vec<LookupResult> {
+        cached_lookup(&self.cache, ips, |ip| self.lookup(ip))
+    }
+}
+
+// Returns results for unique ips, only ips missing from the cache are looked up
+fn cached_lookup(
+    cache: &Mutex<LruCache<IpAddr, LookupResult>>,
+    ips: Vec<IpAddr>,
+    lookup: impl Fn(IpAddr) -> LookupResult,
+) -> Vec<LookupResult> {
+    let mut seen = HashSet::new();
+    let mut results = Vec::with_capacity(ips.len());
+    let mut missing = vec![];
+    {
+        let mut cache = cache.lock().unwrap();
+        for ip in ips.into_iter().filter(|ip| seen.insert(*ip)) {
+            match cache.get(&ip) {
+                Some(result) => results.push(result.clone()),
+                None => missing.push(ip),
+            }
+        }
+    }
+    if missing.is_empty() {
+        return results;
+    }
+
+    let found: Vec<_> = missing.into_iter().map(lookup).collect();
+    let mut cache = cache.lock().unwrap();
+    for result in found {
+        cache.put(result.ip, result.clone());
+        results.push(result);
+    }
+    results
+}
 
 #[derive(Default)]
-pub struct MmdbReaderHandle(pub Arc<Mutex<Option<maxminddb::Reader<Vec<u8>>>>>);
+struct GeoIpState {
+    config: GeoIpConfig,
+    updater: Option<JoinHandle<()>>,
+    // Resolved database files and when they were checked
+    files: Option<(Instant, DbFiles)>,
+}
+
+pub struct MmdbReaderHandle {
+    state: Mutex<GeoIpState>,
+    databases: RwLock<Arc<Databases>>,
+}
+
+impl Default for MmdbReaderHandle {
+    fn default() -> Self {
+        Self {
+            state: Default::default(),
+            databases: RwLock::new(Arc::new(Databases::open(Default::default()))),
+        }
+    }
+}
 
 #[derive(serde::Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -30,57 +219,479 @@ pub struct LookupResult {
     iso_code: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    asn: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    organization: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    city: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<String>,
+}
+
+impl LookupResult {
+    fn new(ip: IpAddr) -> Self {
+        Self {
+            ip,
+            iso_code: None,
+            name: None,
+            asn: None,
+            organization: None,
+            city: None,
+            region: None,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DbInfo {
+    kind: &'static str,
+    path: String,
+    database_type: String,
+    build_epoch: u64,
+}
+
+fn find_db(app: &AppHandle, custom_path: &str, name: &str) -> Option<PathBuf> {
+    if !custom_path.is_empty() {
+        return Some(PathBuf::from(custom_path));
+    }
+    [
+        app.path().app_data_dir().map(|p| p.join(name)),
+        app.path()
+            .resolve(name, tauri::path::BaseDirectory::Resource),
+    ]
+    .into_iter()
+    .filter_map(|p| p.ok())
+    .find(|p| p.is_file())
+}
+
+impl MmdbReaderHandle {
+    fn db_files(&self, app: &AppHandle) -> DbFiles {
+        self.cached_files(|config| {
+            [
+                find_db(app, &config.country_db, COUNTRY_DB),
+                find_db(app, &config.asn_db, ASN_DB),
+                find_db(app, &config.city_db, CITY_DB),
+            ]
+            .map(|p| p.map(DbFile::new))
+        })
+    }
+
+    // Files are resolved again only when the last check is older than the interval
+    fn cached_files(&self, resolve: impl FnOnce(&GeoIpConfig) -> DbFiles) -> DbFiles {
+        let mut state = self.state.lock().unwrap();
+        if let Some((checked, files)) = &state.files {
+            if checked.elapsed() < FILES_CHECK_INTERVAL {
+                return files.clone();
+            }
+        }
+        let files = resolve(&state.config);
+        state.files = Some((Instant::now(), files.clone()));
+        files
+    }
+
+    // Makes the next lookup check the database files again
+    fn invalidate_files(&self) {
+        self.state.lock().unwrap().files = None;
+    }
+
+    // Returns current databases, reopening them first if any of the files changed.
+    // Lookups only hold the lock long enough to clone the Arc.
+    async fn databases(&self, app: &AppHandle) -> Arc<Databases> {
+        self.databases_for(self.db_files(app)).await
+    }
+
+    async fn databases_for(&self, files: DbFiles) -> Arc<Databases> {
+        {
+            let databases = self.databases.read().unwrap();
+            if databases.files == files {
+                return databases.clone();
+            }
+        }
+
+        // Databases are read outside of the lock, concurrent lookups may open
+        // the same files but only one set is kept.
+        let opened = {
+            let files = files.clone();
+            async_runtime::spawn_blocking(move || Databases::open(files)).await
+        };
+        let opened = match opened {
+            Ok(opened) => Arc::new(opened),
+            Err(e) => {
+                println!("Unable to open GeoIP databases: {e}");
+                return self.databases.read().unwrap().clone();
+            }
+        };
+
+        let mut databases = self.databases.write().unwrap();
+        if databases.files != files {
+            *databases = opened;
+        }
+        databases.clone()
+    }
 }
 
 pub async fn lookup(app: &AppHandle, ips: Vec<IpAddr>) -> Vec<LookupResult> {
     let reader_handle: State<MmdbReaderHandle> = app.state();
-    let mut reader = reader_handle.0.lock().await;
-    if reader.is_none() {
-        let dbip_path = app
-            .path()
-            .resolve("dbip.mmdb", tauri::path::BaseDirectory::Resource)
-            .expect("failed to resolve resource");
-
-        if dbip_path.is_file() {
-            match maxminddb::Reader::open_readfile(&dbip_path) {
-                Ok(db) => *reader = Some(db),
-                Err(_) => {
-                    println!("{} is invalid", dbip_path.as_path().display());
-                    return vec![];
+    let databases = reader_handle.databases(app).await;
+    if databases.is_empty() {
+        return vec![];
+    }
+
+    databases.lookup_all(ips)
+}
+
+pub async fn set_config(app: &AppHandle, config: GeoIpConfig) {
+    let reader_handle: State<MmdbReaderHandle> = app.state();
+    let mut state = reader_handle.state.lock().unwrap();
+    if state.config == config {
+        return;
+    }
+
+    if let Some(updater) = state.updater.take() {
+        updater.abort();
+    }
+    state.files = None;
+    if config.country_db.is_empty()
+        && !config.update_url.is_empty()
+        && config.update_interval_days > 0
+    {
+        let app = app.clone();
+        let url = config.update_url.clone();
+        let path = downloaded_db_path(&app);
+        let max_age = Duration::from_secs(config.update_interval_days * 24 * 60 * 60);
+        state.updater = Some(async_runtime::spawn(async move {
+            loop {
+                if update_needed(&path, max_age) {
+                    if let Err(e) = update_db(&app, &url).await {
+                        println!("GeoIP database update failed: {e}");
+                    }
                 }
+                tokio::time::sleep(UPDATE_CHECK_INTERVAL).await;
             }
+        }));
+    }
+    state.config = config;
+}
+
+pub async fn info(app: &AppHandle) -> Vec<DbInfo> {
+    let reader_handle: State<MmdbReaderHandle> = app.state();
+    let databases = reader_handle.databases(app).await;
+
+    DB_KINDS
+        .iter()
+        .zip(databases.files.iter().zip(databases.readers.iter()))
+        .filter_map(|(kind, (file, reader))| {
+            let (file, reader) = (file.as_ref()?, reader.as_ref()?);
+            Some(DbInfo {
+                kind: *kind,
+                path: file.path.to_string_lossy().into(),
+                database_type: reader.metadata.database_type.clone(),
+                build_epoch: reader.metadata.build_epoch,
+            })
+        })
+        .collect()
+}
+
+fn downloaded_db_path(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|p| p.join(COUNTRY_DB))
+        .map_err(|e| format!("Unable to resolve app data dir: {e}"))
+}
+
+fn update_needed(path: &Result<PathBuf, String>, max_age: Duration) -> bool {
+    path.as_ref()
+        .ok()
+        .and_then(|p| p.metadata().ok())
+        .and_then(|m| m.modified().ok())
+        .and_then(|modified| modified.elapsed().ok())
+        .map_or(true, |age| age > max_age)
+}
+
+// Downloads the country database into the app data dir, which is not used
+// while a custom country database is set. Plain and gzip compressed databases
+// are supported. The new file is picked up by the next lookup.
+pub async fn update_db(app: &AppHandle, url: &str) -> Result<(), String> {
+    if url.is_empty() {
+        return Err("GeoIP database update url is not set".to_string());
+    }
+    let reader_handle: State<MmdbReaderHandle> = app.state();
+    if !reader_handle
+        .state
+        .lock()
+        .unwrap()
+        .config
+        .country_db
+        .is_empty()
+    {
+        return Err(
+            "GeoIP database is not updated while a custom country database is set".to_string(),
+        );
+    }
+
+    let client = app
+        .state::<HttpClients>()
+        .get(&ClientSettings::download())?;
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Unable to download {url}: {e}"))?;
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Unable to download {url}: {e}"))?;
+
+    let data = if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut data = vec![];
+        GzipDecoder::new(&bytes[..])
+            .read_to_end(&mut data)
+            .await
+            .map_err(|e| format!("Unable to decompress database: {e}"))?;
+        data
+    } else {
+        bytes.to_vec()
+    };
+
+    check_country_db(&data)?;
+
+    let path = downloaded_db_path(app)?;
+    write_atomic(&path, &data).map_err(|e| format!("Unable to save database: {e}"))?;
+    reader_handle.invalidate_files();
+    println!("GeoIP database updated from {url}");
+
+    Ok(())
+}
+
+// City databases include the country data too
+fn check_country_db(data: &[u8]) -> Result<(), String> {
+    let database_type = maxminddb::Reader::from_source(data)
+        .map(|r| r.metadata.database_type.clone())
+        .map_err(|e| format!("Downloaded file is not a valid database: {e}"))?;
+    if !database_type.contains("Country") && !database_type.contains("City") {
+        return Err(format!(
+            "Downloaded database type {database_type} is not a country database"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        cell::Cell,
+        net::Ipv4Addr,
+        path::Path,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    // Minimal MaxMind DB writer for test databases, see
+    // https://maxmind.github.io/MaxMind-DB/ for the format.
+    enum Value<'a> {
+        Str(&'a str),
+        U16(u16),
+        U32(u32),
+        U64(u64),
+        Array(Vec<Value<'a>>),
+        Map(Vec<(&'a str, Value<'a>)>),
+    }
+
+    fn encode_header(out: &mut Vec<u8>, kind: u8, size: usize) {
+        assert!(size < 29);
+        if kind <= 7 {
+            out.push(kind << 5 | size as u8);
         } else {
-            println!("{} does not exist", dbip_path.as_path().display());
-            return vec![];
+            out.push(size as u8);
+            out.push(kind - 7);
         }
     }
 
-    let reader = reader.as_ref().unwrap();
+    fn encode_uint(out: &mut Vec<u8>, kind: u8, value: u64) {
+        let bytes = value.to_be_bytes();
+        let zeros = bytes.iter().take_while(|b| **b == 0).count();
+        encode_header(out, kind, bytes.len() - zeros);
+        out.extend_from_slice(&bytes[zeros..]);
+    }
 
-    let response: Vec<_> = ips
-        .into_iter()
-        .map(|ip| {
-            match reader
-                .lookup::<maxminddb::geoip2::Country>(ip)
-                .ok()
-                .flatten()
-                .and_then(|c| c.country)
-            {
-                Some(country) => LookupResult {
-                    ip,
-                    iso_code: country.iso_code.map(|c| c.to_string()),
-                    name: country
-                        .names
-                        .and_then(|names| names.get("en").map(|s| s.to_string())),
-                },
-                None => LookupResult {
-                    ip,
-                    iso_code: None,
-                    name: None,
-                },
+    fn encode(out: &mut Vec<u8>, value: &Value) {
+        match value {
+            Value::Str(s) => {
+                encode_header(out, 2, s.len());
+                out.extend_from_slice(s.as_bytes());
             }
-        })
-        .collect();
+            Value::U16(v) => encode_uint(out, 5, *v as u64),
+            Value::U32(v) => encode_uint(out, 6, *v as u64),
+            Value::U64(v) => encode_uint(out, 9, *v),
+            Value::Array(items) => {
+                encode_header(out, 11, items.len());
+                items.iter().for_each(|item| encode(out, item));
+            }
+            Value::Map(entries) => {
+                encode_header(out, 7, entries.len());
+                for (key, value) in entries {
+                    encode(out, &Value::Str(key));
+                    encode(out, value);
+                }
+            }
+        }
+    }
+
+    // IPv4 database where every address is in the country with the iso code
+    fn test_db(database_type: &str, iso_code: &str) -> Vec<u8> {
+        let node_count = 1u32;
+        // Both records of the only node point to the start of the data section
+        let record = (node_count + 16).to_be_bytes();
+        let mut db = [&record[1..], &record[1..], &[0; 16]].concat();
+        encode(
+            &mut db,
+            &Value::Map(vec![(
+                "country",
+                Value::Map(vec![
+                    ("iso_code", Value::Str(iso_code)),
+                    ("names", Value::Map(vec![("en", Value::Str(iso_code))])),
+                ]),
+            )]),
+        );
+        db.extend_from_slice(b"\xab\xcd\xefMaxMind.com");
+        encode(
+            &mut db,
+            &Value::Map(vec![
+                ("node_count", Value::U32(node_count)),
+                ("record_size", Value::U16(24)),
+                ("ip_version", Value::U16(4)),
+                ("database_type", Value::Str(database_type)),
+                ("languages", Value::Array(vec![Value::Str("en")])),
+                ("binary_format_major_version", Value::U16(2)),
+                ("binary_format_minor_version", Value::U16(0)),
+                ("build_epoch", Value::U64(1_700_000_000)),
+                ("description", Value::Map(vec![])),
+            ]),
+        );
+        db
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("trguing-geoip-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    fn set_age(path: &Path, age: Duration) {
+        std::fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() - age)
+            .unwrap();
+    }
+
+    const IP: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+
+    fn iso_code(databases: &Databases) -> Option<String> {
+        databases.lookup(IP).iso_code
+    }
 
-    response
+    #[test]
+    fn checks_country_db() {
+        assert!(check_country_db(&test_db("DBIP-Country-Lite", "DE")).is_ok());
+        assert!(check_country_db(&test_db("GeoLite2-City", "DE")).is_ok());
+        assert!(check_country_db(&test_db("GeoLite2-ASN", "DE"))
+            .unwrap_err()
+            .contains("not a country database"));
+        assert!(check_country_db(b"<html></html>")
+            .unwrap_err()
+            .contains("not a valid database"));
+    }
+
+    #[test]
+    fn reopens_changed_databases() {
+        let path = temp_path("reopen.mmdb");
+        std::fs::write(&path, test_db("DBIP-Country-Lite", "DE")).unwrap();
+        set_age(&path, Duration::from_secs(60 * 60));
+        let files = || [Some(DbFile::new(path.clone())), None, None];
+        let handle = MmdbReaderHandle::default();
+
+        let first = async_runtime::block_on(handle.databases_for(files()));
+        assert_eq!(iso_code(&first).as_deref(), Some("DE"));
+        let same = async_runtime::block_on(handle.databases_for(files()));
+        assert!(Arc::ptr_eq(&first, &same));
+
+        std::fs::write(&path, test_db("DBIP-Country-Lite", "FR")).unwrap();
+        set_age(&path, Duration::from_secs(60));
+        let reopened = async_runtime::block_on(handle.databases_for(files()));
+        assert!(!Arc::ptr_eq(&first, &reopened));
+        assert_eq!(iso_code(&reopened).as_deref(), Some("FR"));
+        // Databases that are still in use are not affected
+        assert_eq!(iso_code(&first).as_deref(), Some("DE"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn caches_resolved_files() {
+        let handle = MmdbReaderHandle::default();
+        let resolved = Cell::new(0);
+        let resolve = |_: &GeoIpConfig| -> DbFiles {
+            resolved.set(resolved.get() + 1);
+            Default::default()
+        };
+
+        handle.cached_files(resolve);
+        handle.cached_files(resolve);
+        assert_eq!(resolved.get(), 1);
+
+        handle.invalidate_files();
+        handle.cached_files(resolve);
+        assert_eq!(resolved.get(), 2);
+
+        handle.state.lock().unwrap().files = Some((
+            Instant::now().checked_sub(FILES_CHECK_INTERVAL).unwrap(),
+            Default::default(),
+        ));
+        handle.cached_files(resolve);
+        assert_eq!(resolved.get(), 3);
+    }
+
+    #[test]
+    fn checks_update_age() {
+        let day = Duration::from_secs(24 * 60 * 60);
+        let path = temp_path("age.mmdb");
+        assert!(update_needed(&Err("no app data dir".to_string()), day));
+        assert!(update_needed(&Ok(path.clone()), day));
+
+        std::fs::write(&path, b"").unwrap();
+        assert!(!update_needed(&Ok(path.clone()), day));
+        set_age(&path, 2 * day);
+        assert!(update_needed(&Ok(path.clone()), day));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn looks_up_each_ip_once() {
+        let cache = Mutex::new(LruCache::new(NonZeroUsize::new(2).unwrap()));
+        let lookups = AtomicUsize::new(0);
+        let lookup = |ip| {
+            lookups.fetch_add(1, Ordering::SeqCst);
+            LookupResult::new(ip)
+        };
+        let ip = |last| IpAddr::V4(Ipv4Addr::new(192, 0, 2, last));
+
+        let results = cached_lookup(&cache, vec![ip(1), ip(2), ip(1)], lookup);
+        assert_eq!(results.len(), 2);
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+
+        let results = cached_lookup(&cache, vec![ip(2), ip(1), ip(2)], lookup);
+        assert_eq!(results.len(), 2);
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+
+        // Least recently used address is dropped from the full cache
+        cached_lookup(&cache, vec![ip(3)], lookup);
+        cached_lookup(&cache, vec![ip(1), ip(2)], lookup);
+        assert_eq!(lookups.load(Ordering::SeqCst), 4);
+    }
 }
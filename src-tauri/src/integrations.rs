@@ -20,6 +20,10 @@ use winreg::RegKey;
 #[cfg(target_os = "windows")]
 const APP_NAME: &str = "TrguiNG";
 
+// Apps started on login stay in the tray until opened
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+const AUTOSTART_ARGS: &str = "--tray";
+
 #[cfg(target_os = "windows")]
 fn register_app_class() -> std::io::Result<()> {
     match std::env::current_exe() {
@@ -42,17 +46,104 @@ fn register_app_class() -> std::io::Result<()> {
     Ok(())
 }
 
+// Handlers that were registered before the app took over are kept in the app class
+// key so that they can be restored when the association is removed.
+#[cfg(target_os = "windows")]
+const PREVIOUS_TORRENT_CLASS: &str = "PreviousTorrentClass";
+#[cfg(target_os = "windows")]
+const PREVIOUS_MAGNET_COMMAND: &str = "PreviousMagnetCommand";
+#[cfg(target_os = "windows")]
+const PREVIOUS_MAGNET_ICON: &str = "PreviousMagnetIcon";
+
+#[cfg(target_os = "windows")]
+fn app_class_key() -> std::io::Result<RegKey> {
+    let hkcu = RegKey::predef(winreg::enums::HKEY_CURRENT_USER);
+    let (key, _) = hkcu.create_subkey(format!("SOFTWARE\\Classes\\{APP_NAME}"))?;
+    Ok(key)
+}
+
+#[cfg(target_os = "windows")]
+fn open_command() -> Option<String> {
+    match std::env::current_exe() {
+        Ok(exe) => Some(format!("\"{}\" \"%1\"", exe.to_str().unwrap_or_default())),
+        Err(e) => {
+            println!("Error getting exe path: {e}");
+            None
+        }
+    }
+}
+
 #[cfg(target_os = "windows")]
 fn register_torrent_class() -> std::io::Result<()> {
     let hkcu = RegKey::predef(winreg::enums::HKEY_CURRENT_USER);
     let (key, _) = hkcu.create_subkey("SOFTWARE\\Classes\\.torrent")?;
+    if let Ok(previous) = key.get_value::<String, &str>("") {
+        if previous != APP_NAME && !previous.is_empty() {
+            app_class_key()?.set_value(PREVIOUS_TORRENT_CLASS, &previous)?;
+        }
+    }
     key.set_value("", &APP_NAME)?;
 
     Ok(())
 }
 
+#[cfg(target_os = "windows")]
+fn unregister_torrent_class() -> std::io::Result<()> {
+    if !check_torrent_class() {
+        return Ok(());
+    }
+    let hkcu = RegKey::predef(winreg::enums::HKEY_CURRENT_USER);
+    let (key, _) = hkcu.create_subkey("SOFTWARE\\Classes\\.torrent")?;
+    let app_class = app_class_key()?;
+    match app_class.get_value::<String, &str>(PREVIOUS_TORRENT_CLASS) {
+        Ok(previous) => {
+            key.set_value("", &previous)?;
+            app_class.delete_value(PREVIOUS_TORRENT_CLASS)?;
+        }
+        Err(_) => key.delete_value("")?,
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn check_torrent_class() -> bool {
+    let hkcu = RegKey::predef(winreg::enums::HKEY_CURRENT_USER);
+    hkcu.open_subkey("SOFTWARE\\Classes\\.torrent")
+        .and_then(|key| key.get_value::<String, &str>(""))
+        .is_ok_and(|class| class == APP_NAME)
+}
+
+#[cfg(target_os = "windows")]
+fn save_previous_magnet_handler() -> std::io::Result<()> {
+    let hkcu = RegKey::predef(winreg::enums::HKEY_CURRENT_USER);
+    let Ok(command) = hkcu
+        .open_subkey("SOFTWARE\\Classes\\Magnet\\shell\\open\\command")
+        .and_then(|key| key.get_value::<String, &str>(""))
+    else {
+        return Ok(());
+    };
+    if Some(&command) == open_command().as_ref() {
+        return Ok(());
+    }
+
+    let app_class = app_class_key()?;
+    app_class.set_value(PREVIOUS_MAGNET_COMMAND, &command)?;
+    match hkcu
+        .open_subkey("SOFTWARE\\Classes\\Magnet\\DefaultIcon")
+        .and_then(|key| key.get_value::<String, &str>(""))
+    {
+        Ok(icon) => app_class.set_value(PREVIOUS_MAGNET_ICON, &icon)?,
+        Err(_) => {
+            let _ = app_class.delete_value(PREVIOUS_MAGNET_ICON);
+        }
+    }
+    Ok(())
+}
+
 #[cfg(target_os = "windows")]
 fn register_magnet_class() -> std::io::Result<()> {
+    save_previous_magnet_handler()?;
     match std::env::current_exe() {
         Ok(exe) => {
             let exe = exe.to_str().unwrap_or_default();
@@ -78,16 +169,56 @@ fn register_magnet_class() -> std::io::Result<()> {
     Ok(())
 }
 
+#[cfg(target_os = "windows")]
+fn unregister_magnet_class() -> std::io::Result<()> {
+    if !check_magnet_class() {
+        return Ok(());
+    }
+    let hkcu = RegKey::predef(winreg::enums::HKEY_CURRENT_USER);
+    let app_class = app_class_key()?;
+    match app_class.get_value::<String, &str>(PREVIOUS_MAGNET_COMMAND) {
+        Ok(command) => {
+            let (key, _) = hkcu.create_subkey("SOFTWARE\\Classes\\Magnet\\shell\\open\\command")?;
+            key.set_value("", &command)?;
+            let (key, _) = hkcu.create_subkey("SOFTWARE\\Classes\\Magnet\\DefaultIcon")?;
+            match app_class.get_value::<String, &str>(PREVIOUS_MAGNET_ICON) {
+                Ok(icon) => key.set_value("", &icon)?,
+                Err(_) => key.delete_value("")?,
+            }
+            app_class.delete_value(PREVIOUS_MAGNET_COMMAND)?;
+            let _ = app_class.delete_value(PREVIOUS_MAGNET_ICON);
+        }
+        Err(_) => hkcu.delete_subkey_all("SOFTWARE\\Classes\\Magnet")?,
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn check_magnet_class() -> bool {
+    let hkcu = RegKey::predef(winreg::enums::HKEY_CURRENT_USER);
+    let command = hkcu
+        .open_subkey("SOFTWARE\\Classes\\Magnet\\shell\\open\\command")
+        .and_then(|key| key.get_value::<String, &str>(""));
+    matches!((command, open_command()), (Ok(command), Some(ours)) if command == ours)
+}
+
+// Quoted so that paths with spaces are not split by the shell
+#[cfg(target_os = "windows")]
+fn autorun_command(exe: &str) -> String {
+    format!("\"{exe}\" {AUTOSTART_ARGS}")
+}
+
 #[cfg(target_os = "windows")]
 fn register_autorun(run: bool) -> std::io::Result<()> {
     match std::env::current_exe() {
         Ok(exe) => {
-            let exe = format!("\"{}\"", exe.to_str().unwrap_or_default());
+            let exe = exe.to_str().unwrap_or_default();
             let hkcu = RegKey::predef(winreg::enums::HKEY_CURRENT_USER);
             let (key, _) =
                 hkcu.create_subkey("SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Run")?;
             if run {
-                key.set_value(APP_NAME, &exe)?;
+                key.set_value(APP_NAME, &autorun_command(exe))?;
             } else {
                 key.delete_value(APP_NAME)?;
             }
@@ -101,13 +232,14 @@ fn register_autorun(run: bool) -> std::io::Result<()> {
 fn check_autorun() -> bool {
     match std::env::current_exe() {
         Ok(exe) => {
-            let exe = format!("\"{}\"", exe.to_str().unwrap_or_default());
+            let exe = exe.to_str().unwrap_or_default();
             let hkcu = RegKey::predef(winreg::enums::HKEY_CURRENT_USER);
             if let Ok((key, _)) =
                 hkcu.create_subkey("SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Run")
             {
                 if let Ok(val) = key.get_value::<String, &str>(APP_NAME) {
-                    return val == exe;
+                    // Older versions registered the exe without arguments
+                    return val == autorun_command(exe) || val == format!("\"{exe}\"");
                 }
             }
         }
@@ -137,6 +269,24 @@ pub fn app_integration_impl(mode: String) -> bool {
                 println!("Error writing to registry: {e}");
             }
         }
+        "notorrent" => {
+            println!("Removing .torrent file association");
+            if let Err(e) = unregister_torrent_class() {
+                println!("Error writing to registry: {e}");
+            }
+        }
+        "nomagnet" => {
+            println!("Removing magnet link association");
+            if let Err(e) = unregister_magnet_class() {
+                println!("Error writing to registry: {e}");
+            }
+        }
+        "gettorrent" => {
+            return check_torrent_class();
+        }
+        "getmagnet" => {
+            return check_magnet_class();
+        }
         "autostart" => {
             println!("Adding app to auto start");
             if let Err(e) = register_autorun(true) {
@@ -160,7 +310,434 @@ pub fn app_integration_impl(mode: String) -> bool {
     false
 }
 
-#[cfg(not(target_os = "windows"))]
+#[cfg(target_os = "linux")]
+mod xdg {
+    use std::{
+        fs, io,
+        path::{Path, PathBuf},
+    };
+
+    use super::AUTOSTART_ARGS;
+
+    // Same id as the desktop file installed by the deb and rpm packages so that
+    // the user copy overrides it instead of adding a second menu entry.
+    pub const DESKTOP_ID: &str = "TrguiNG.desktop";
+    pub const TORRENT_MIME: &str = "application/x-bittorrent";
+    pub const MAGNET_MIME: &str = "x-scheme-handler/magnet";
+    const DEFAULT_APPLICATIONS: &str = "Default Applications";
+    const ADDED_ASSOCIATIONS: &str = "Added Associations";
+
+    fn home_dir(dir: Option<PathBuf>) -> io::Result<PathBuf> {
+        dir.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Unable to find home dir"))
+    }
+
+    // AppImage executables are mounted at a random path on each run
+    fn exe_path() -> io::Result<String> {
+        match std::env::var("APPIMAGE") {
+            Ok(appimage) if !appimage.is_empty() => Ok(appimage),
+            _ => Ok(std::env::current_exe()?.to_string_lossy().into()),
+        }
+    }
+
+    // Exec key quoting rules, backslashes are escaped twice because the value
+    // is also subject to the general string escaping of desktop files. Percent
+    // signs are doubled so that they are not taken as field codes.
+    fn quote_exec(arg: &str) -> String {
+        if !arg
+            .chars()
+            .any(|c| c.is_whitespace() || "\"'\\><~|&;$*?#()`%".contains(c))
+        {
+            return arg.to_string();
+        }
+        let mut quoted = String::from("\"");
+        for c in arg.chars() {
+            match c {
+                '"' | '`' | '$' => quoted.push_str("\\\\"),
+                '\\' => quoted.push_str("\\\\\\"),
+                '%' => quoted.push('%'),
+                _ => {}
+            }
+            quoted.push(c);
+        }
+        quoted.push('"');
+        quoted
+    }
+
+    // The template is shared with the packages, its Exec line is replaced as a
+    // whole so that the arguments don't depend on how the template ends it.
+    fn render_desktop_entry(exec: &str, args: &str) -> String {
+        include_str!("../app.desktop")
+            .replace("{{{categories}}}", "Network;FileTransfer;P2P;")
+            .replace("{{{icon}}}", "TrguiNG")
+            .replace("{{{name}}}", "TrguiNG")
+            .replace(
+                "{{{comment}}}",
+                "Remote control GUI for Transmission torrent daemon",
+            )
+            .lines()
+            .map(|line| {
+                if line.starts_with("Exec=") {
+                    format!("Exec={exec} {args}\n")
+                } else {
+                    format!("{line}\n")
+                }
+            })
+            .collect()
+    }
+
+    fn write_file(path: &Path, contents: &str) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)
+    }
+
+    fn applications_dir() -> io::Result<PathBuf> {
+        Ok(home_dir(dirs::data_dir())?.join("applications"))
+    }
+
+    fn install_desktop_file() -> io::Result<()> {
+        let exec = quote_exec(&exe_path()?);
+        let dir = applications_dir()?;
+        write_file(&dir.join(DESKTOP_ID), &render_desktop_entry(&exec, "%u"))?;
+        // Refreshes the mime cache of desktop environments that use it, not required
+        let _ = std::process::Command::new("update-desktop-database")
+            .arg(dir)
+            .output();
+        Ok(())
+    }
+
+    fn mimeapps_path() -> io::Result<PathBuf> {
+        Ok(home_dir(dirs::config_dir())?.join("mimeapps.list"))
+    }
+
+    fn autostart_path() -> io::Result<PathBuf> {
+        Ok(home_dir(dirs::config_dir())?
+            .join("autostart")
+            .join(DESKTOP_ID))
+    }
+
+    // Minimal editor of mimeapps.list that keeps all unrelated lines as they are
+    struct MimeApps {
+        lines: Vec<String>,
+    }
+
+    fn section_name(line: &str) -> Option<&str> {
+        line.trim()
+            .strip_prefix('[')
+            .and_then(|l| l.strip_suffix(']'))
+    }
+
+    impl MimeApps {
+        fn load() -> io::Result<Self> {
+            match fs::read_to_string(mimeapps_path()?) {
+                Ok(contents) => Ok(Self::parse(&contents)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::parse("")),
+                Err(e) => Err(e),
+            }
+        }
+
+        fn parse(contents: &str) -> Self {
+            Self {
+                lines: contents.lines().map(|l| l.to_string()).collect(),
+            }
+        }
+
+        fn contents(&self) -> String {
+            let mut contents = self.lines.join("\n");
+            contents.push('\n');
+            contents
+        }
+
+        fn save(&self) -> io::Result<()> {
+            write_file(&mimeapps_path()?, &self.contents())
+        }
+
+        // Returns index of the line with the key and the index after the last line
+        // of the section, if the section exists.
+        fn find(&self, section: &str, key: &str) -> (Option<usize>, Option<usize>) {
+            let mut in_section = false;
+            let mut key_line = None;
+            let mut section_end = None;
+            for (i, line) in self.lines.iter().enumerate() {
+                if let Some(name) = section_name(line) {
+                    in_section = name == section;
+                    continue;
+                }
+                if !in_section {
+                    continue;
+                }
+                if !line.trim().is_empty() {
+                    section_end = Some(i + 1);
+                }
+                if line.split_once('=').is_some_and(|(k, _)| k.trim() == key) {
+                    key_line = Some(i);
+                }
+            }
+            if section_end.is_none() {
+                section_end = self
+                    .lines
+                    .iter()
+                    .position(|l| section_name(l) == Some(section))
+                    .map(|i| i + 1);
+            }
+            (key_line, section_end)
+        }
+
+        fn get(&self, section: &str, key: &str) -> Vec<String> {
+            match self.find(section, key) {
+                (Some(i), _) => self.lines[i]
+                    .split_once('=')
+                    .map(|(_, v)| v)
+                    .unwrap_or_default()
+                    .split(';')
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty())
+                    .collect(),
+                _ => vec![],
+            }
+        }
+
+        fn set(&mut self, section: &str, key: &str, values: &[String]) {
+            let line = format!("{key}={};", values.join(";"));
+            match self.find(section, key) {
+                (Some(i), _) if values.is_empty() => {
+                    self.lines.remove(i);
+                }
+                (Some(i), _) => self.lines[i] = line,
+                (None, _) if values.is_empty() => {}
+                (None, Some(end)) => self.lines.insert(end, line),
+                (None, None) => {
+                    if self.lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                        self.lines.push(String::new());
+                    }
+                    self.lines.push(format!("[{section}]"));
+                    self.lines.push(line);
+                }
+            }
+        }
+
+        fn add_handler(&mut self, mime: &str) {
+            for section in [DEFAULT_APPLICATIONS, ADDED_ASSOCIATIONS] {
+                let mut handlers = self.get(section, mime);
+                handlers.retain(|h| h != DESKTOP_ID);
+                handlers.insert(0, DESKTOP_ID.to_string());
+                self.set(section, mime, &handlers);
+            }
+        }
+
+        // Handlers that were registered before are kept after the app in the lists,
+        // so removing the app makes the previous default handler current again.
+        fn remove_handler(&mut self, mime: &str) {
+            for section in [DEFAULT_APPLICATIONS, ADDED_ASSOCIATIONS] {
+                let mut handlers = self.get(section, mime);
+                handlers.retain(|h| h != DESKTOP_ID);
+                self.set(section, mime, &handlers);
+            }
+        }
+
+        fn is_default(&self, mime: &str) -> bool {
+            self.get(DEFAULT_APPLICATIONS, mime)
+                .first()
+                .is_some_and(|h| h == DESKTOP_ID)
+        }
+    }
+
+    pub fn set_default_handler(mime: &str) -> io::Result<()> {
+        install_desktop_file()?;
+
+        let mut mimeapps = MimeApps::load()?;
+        mimeapps.add_handler(mime);
+        mimeapps.save()
+    }
+
+    pub fn remove_default_handler(mime: &str) -> io::Result<()> {
+        let mut mimeapps = MimeApps::load()?;
+        mimeapps.remove_handler(mime);
+        mimeapps.save()
+    }
+
+    pub fn is_default_handler(mime: &str) -> bool {
+        MimeApps::load().is_ok_and(|mimeapps| mimeapps.is_default(mime))
+    }
+
+    pub fn set_autostart(run: bool) -> io::Result<()> {
+        let path = autostart_path()?;
+        if run {
+            let exec = quote_exec(&exe_path()?);
+            let entry = render_desktop_entry(&exec, AUTOSTART_ARGS);
+            write_file(&path, &format!("{entry}X-GNOME-Autostart-enabled=true\n"))
+        } else {
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    pub fn check_autostart() -> bool {
+        autostart_path()
+            .and_then(fs::read_to_string)
+            .is_ok_and(|entry| {
+                !entry.lines().any(|l| {
+                    l.trim() == "Hidden=true" || l.trim() == "X-GNOME-Autostart-enabled=false"
+                })
+            })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn quotes_exec_arguments() {
+            assert_eq!(quote_exec("/usr/bin/trgui-ng"), "/usr/bin/trgui-ng");
+            assert_eq!(
+                quote_exec("/home/user/My Apps/TrguiNG.AppImage"),
+                "\"/home/user/My Apps/TrguiNG.AppImage\""
+            );
+            // Each escaped character gets one exec and one string level backslash
+            assert_eq!(quote_exec("/opt/a\"b"), r#""/opt/a\\"b""#);
+            assert_eq!(quote_exec("/opt/$HOME"), r#""/opt/\\$HOME""#);
+            assert_eq!(quote_exec("/opt/a\\b"), r#""/opt/a\\\\b""#);
+            assert_eq!(quote_exec("/opt/100%/app"), "\"/opt/100%%/app\"");
+        }
+
+        #[test]
+        fn renders_exec_line() {
+            let entry = render_desktop_entry("/usr/bin/trgui-ng", "%u");
+            assert!(entry.contains("\nExec=/usr/bin/trgui-ng %u\n"));
+            assert!(!entry.contains("{{{"));
+
+            let entry = render_desktop_entry("\"/opt/100%%/app\"", AUTOSTART_ARGS);
+            let exec: Vec<&str> = entry.lines().filter(|l| l.starts_with("Exec=")).collect();
+            assert_eq!(exec, [format!("Exec=\"/opt/100%%/app\" {AUTOSTART_ARGS}")]);
+        }
+
+        const EXISTING: &str = "\
+[Default Applications]
+text/html=firefox.desktop;
+x-scheme-handler/magnet=transmission-gtk.desktop;
+
+[Added Associations]
+x-scheme-handler/magnet=transmission-gtk.desktop;
+";
+
+        #[test]
+        fn adds_and_removes_handler() {
+            let mut mimeapps = MimeApps::parse(EXISTING);
+            assert!(!mimeapps.is_default(MAGNET_MIME));
+
+            mimeapps.add_handler(MAGNET_MIME);
+            assert!(mimeapps.is_default(MAGNET_MIME));
+            assert_eq!(
+                mimeapps.contents(),
+                "\
+[Default Applications]
+text/html=firefox.desktop;
+x-scheme-handler/magnet=TrguiNG.desktop;transmission-gtk.desktop;
+
+[Added Associations]
+x-scheme-handler/magnet=TrguiNG.desktop;transmission-gtk.desktop;
+"
+            );
+
+            // Adding again does not duplicate the handler
+            mimeapps.add_handler(MAGNET_MIME);
+            assert_eq!(
+                mimeapps.get(DEFAULT_APPLICATIONS, MAGNET_MIME),
+                ["TrguiNG.desktop", "transmission-gtk.desktop"]
+            );
+
+            mimeapps.remove_handler(MAGNET_MIME);
+            assert_eq!(mimeapps.contents(), EXISTING);
+        }
+
+        #[test]
+        fn creates_missing_sections() {
+            let mut mimeapps = MimeApps::parse("[Removed Associations]\ntext/plain=vim.desktop;\n");
+            mimeapps.add_handler(TORRENT_MIME);
+            assert_eq!(
+                mimeapps.contents(),
+                "\
+[Removed Associations]
+text/plain=vim.desktop;
+
+[Default Applications]
+application/x-bittorrent=TrguiNG.desktop;
+
+[Added Associations]
+application/x-bittorrent=TrguiNG.desktop;
+"
+            );
+
+            mimeapps.remove_handler(TORRENT_MIME);
+            assert!(!mimeapps.is_default(TORRENT_MIME));
+            assert!(mimeapps.get(ADDED_ASSOCIATIONS, TORRENT_MIME).is_empty());
+            assert!(MimeApps::parse("")
+                .get(DEFAULT_APPLICATIONS, TORRENT_MIME)
+                .is_empty());
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn app_integration_impl(mode: String) -> bool {
+    match mode.as_str() {
+        "torrent" => {
+            println!("Associating .torrent files with the app");
+            if let Err(e) = xdg::set_default_handler(xdg::TORRENT_MIME) {
+                println!("Error writing mime associations: {e}");
+            }
+        }
+        "magnet" => {
+            println!("Associating magnet links with the app");
+            if let Err(e) = xdg::set_default_handler(xdg::MAGNET_MIME) {
+                println!("Error writing mime associations: {e}");
+            }
+        }
+        "notorrent" => {
+            println!("Removing .torrent file association");
+            if let Err(e) = xdg::remove_default_handler(xdg::TORRENT_MIME) {
+                println!("Error writing mime associations: {e}");
+            }
+        }
+        "nomagnet" => {
+            println!("Removing magnet link association");
+            if let Err(e) = xdg::remove_default_handler(xdg::MAGNET_MIME) {
+                println!("Error writing mime associations: {e}");
+            }
+        }
+        "gettorrent" => {
+            return xdg::is_default_handler(xdg::TORRENT_MIME);
+        }
+        "getmagnet" => {
+            return xdg::is_default_handler(xdg::MAGNET_MIME);
+        }
+        "autostart" => {
+            println!("Adding app to auto start");
+            if let Err(e) = xdg::set_autostart(true) {
+                println!("Error writing autostart entry: {e}");
+            }
+        }
+        "noautostart" => {
+            println!("Removing app from auto start");
+            if let Err(e) = xdg::set_autostart(false) {
+                println!("Error removing autostart entry: {e}");
+            }
+        }
+        "getautostart" => {
+            println!("Checking auto start");
+            return xdg::check_autostart();
+        }
+        _ => {
+            println!("Bad app_integration call");
+        }
+    }
+    false
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
 pub fn app_integration_impl(_mode: String) -> bool {
     false
 }
@@ -20,21 +20,32 @@ use std::net::IpAddr;
 use std::sync::Arc;
 use std::time::Duration;
 
+use http_body_util::channel::{Channel, Sender};
 use http_body_util::combinators::BoxBody;
 use http_body_util::{BodyExt, Empty, Full};
 use hyper::body::{Bytes, Incoming};
 use hyper::header::{
     self, HeaderName, HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
-    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, AUTHORIZATION, ORIGIN,
+    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, AUTHORIZATION, CONTENT_ENCODING,
+    CONTENT_LENGTH, ORIGIN, VARY,
 };
 use hyper::service::service_fn;
 use hyper::{HeaderMap, Method, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
 use tauri::{async_runtime, AppHandle, Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
 use tokio::net::TcpListener;
+use tokio::sync::mpsc;
 use tokio::sync::oneshot::Receiver;
 use tokio::sync::{oneshot, OwnedSemaphorePermit, Semaphore};
 
-use crate::torrentcache::process_response;
+use crate::appconfig::AppConfig;
+use crate::compression::{ContentEncoder, MIN_COMPRESS_SIZE};
+use crate::httpclient::{ClientSettings, HttpClients};
+use crate::rdns::ReverseDns;
+use crate::rpc::{AddOptions, Rpc};
+use crate::session::TransmissionSessions;
+use crate::torrentcache::spawn_response_parser;
 use crate::tray::toggle_main_window;
 
 const ADDRESS: &str = "127.0.0.1:44321";
@@ -43,40 +54,70 @@ const ALLOW_ORIGINS: &[&str] = if cfg!(feature = "custom-protocol") {
 } else {
     &["http://localhost:8080"]
 };
-const TRANSMISSION_SESSION_ID: &str = "X-Transmission-Session-Id";
+// Tells the frontend which kind of failure happened when the proxy could not reach the server
+const TRGUING_ERROR: &str = "X-TrguiNG-Error";
+const TRGUING_CLIENT: &str = "X-TrguiNG-Client";
+const PROXY_BODY_CHANNEL_SIZE: usize = 16;
+// How long the proxied response waits for a busy torrent cache parser before giving up on it
+const PARSER_SEND_TIMEOUT: Duration = Duration::from_millis(500);
+
+type BoxError = Box<dyn Error + Send + Sync>;
 
 #[derive(Clone, serde::Serialize)]
 struct Payload(String);
 
-fn not_found() -> Response<BoxBody<Bytes, hyper::Error>> {
+fn not_found() -> Response<BoxBody<Bytes, BoxError>> {
     Response::builder()
         .status(StatusCode::NOT_FOUND)
         .body(make_body("NOT FOUND"))
         .unwrap()
 }
 
-fn timed_out(request_headers: &HeaderMap) -> Response<BoxBody<Bytes, hyper::Error>> {
+fn timed_out(request_headers: &HeaderMap) -> Response<BoxBody<Bytes, BoxError>> {
     let mut response = Response::builder()
         .status(StatusCode::REQUEST_TIMEOUT)
+        .header(TRGUING_ERROR, "timeout")
         .body(make_body("Request timed out"))
         .unwrap();
     cors(request_headers, &mut response, ALLOW_ORIGINS);
     response
 }
 
-fn fetch_error(request_headers: &HeaderMap) -> Response<BoxBody<Bytes, hyper::Error>> {
+fn connection_failed(request_headers: &HeaderMap, msg: &str) -> Response<BoxBody<Bytes, BoxError>> {
     let mut response = Response::builder()
-        .status(StatusCode::SERVICE_UNAVAILABLE)
-        .body(make_body("Proxy fetch error"))
+        .status(StatusCode::BAD_GATEWAY)
+        .header(TRGUING_ERROR, "connect")
+        .body(make_body(format!("Connection failed: {msg}")))
         .unwrap();
     cors(request_headers, &mut response, ALLOW_ORIGINS);
     response
 }
 
-fn invalid_request(
+fn fetch_error(
     request_headers: &HeaderMap,
+    status: StatusCode,
     msg: &str,
-) -> Response<BoxBody<Bytes, hyper::Error>> {
+) -> Response<BoxBody<Bytes, BoxError>> {
+    let mut response = Response::builder()
+        .status(status)
+        .body(make_body(msg.to_string()))
+        .unwrap();
+    cors(request_headers, &mut response, ALLOW_ORIGINS);
+    response
+}
+
+// reqwest errors carry the actual cause, like "connection refused", in their sources
+fn error_chain(e: &dyn Error) -> String {
+    let mut msg = e.to_string();
+    let mut source = e.source();
+    while let Some(e) = source {
+        msg = format!("{msg}: {e}");
+        source = e.source();
+    }
+    msg
+}
+
+fn invalid_request(request_headers: &HeaderMap, msg: &str) -> Response<BoxBody<Bytes, BoxError>> {
     let mut response = Response::builder()
         .status(StatusCode::BAD_REQUEST)
         .body(make_body(format!("INVALID REQUEST: {msg}")))
@@ -100,7 +141,7 @@ fn cors<T>(request_headers: &HeaderMap, r: &mut Response<T>, allowed_origins: &[
     if !r.headers().contains_key(ACCESS_CONTROL_EXPOSE_HEADERS) {
         r.headers_mut().append(
             ACCESS_CONTROL_EXPOSE_HEADERS,
-            HeaderValue::from_static("X-Transmission-Session-Id"),
+            HeaderValue::from_static("X-Transmission-Session-Id, X-TrguiNG-Error"),
         );
     }
     r.headers_mut().append(
@@ -109,7 +150,7 @@ fn cors<T>(request_headers: &HeaderMap, r: &mut Response<T>, allowed_origins: &[
     );
 }
 
-fn make_body<T: Into<Bytes>>(chunk: T) -> BoxBody<Bytes, hyper::Error> {
+fn make_body<T: Into<Bytes>>(chunk: T) -> BoxBody<Bytes, BoxError> {
     Full::new(chunk.into())
         .map_err(|never| match never {})
         .boxed()
@@ -119,7 +160,7 @@ async fn http_response(
     app: AppHandle,
     args_lock: Arc<Semaphore>,
     req: Request<Incoming>,
-) -> hyper::Result<Response<BoxBody<Bytes, hyper::Error>>> {
+) -> hyper::Result<Response<BoxBody<Bytes, BoxError>>> {
     let content_type = req
         .headers()
         .get("Content-Type")
@@ -133,6 +174,17 @@ async fn http_response(
         (&Method::POST, "/args") => {
             let payload = req.collect().await?.to_bytes();
 
+            if let Ok(args) = serde_json::from_slice::<AddArgs>(&payload) {
+                if args.no_dialog {
+                    match add_without_dialog(&app, &args).await {
+                        Ok(()) => {
+                            return Ok(Response::builder().body(make_body("TrguiNG OK")).unwrap())
+                        }
+                        Err(e) => println!("Unable to add torrents without dialog: {e}"),
+                    }
+                }
+            }
+
             if app.get_webview_window("main").is_none() {
                 toggle_main_window(&app, None);
             }
@@ -154,53 +206,78 @@ async fn http_response(
         (&Method::POST, "/post") => proxy_fetch(&app, req, false).await,
         (&Method::POST, "/torrentget") => proxy_fetch(&app, req, true).await,
         (&Method::POST, "/iplookup") => geoip_lookup(&app, req).await,
+        (&Method::POST, "/rdns") => rdns_lookup(&app, req).await,
         _ => Ok(not_found()),
     }
 }
 
+fn parse_ips(request_bytes: &[u8]) -> Result<Vec<IpAddr>, String> {
+    let ipstr = serde_json::from_slice::<Vec<String>>(request_bytes)
+        .map_err(|e| format!("Can not parse json: {e}\n"))?;
+
+    let ips: Vec<_> = ipstr.iter().map(|ip| ip.parse::<IpAddr>()).collect();
+    let error_ips: Vec<_> = ips
+        .iter()
+        .enumerate()
+        .filter(|(_, ip)| ip.is_err())
+        .map(|(i, r)| (i, r.clone().err().unwrap()))
+        .collect();
+    if !error_ips.is_empty() {
+        let msgs: Vec<_> = error_ips
+            .into_iter()
+            .map(|(i, e)| format!("{}: {}", ipstr[i], e))
+            .collect();
+        return Err(format!("{}\n", msgs.join("\n")));
+    }
+
+    Ok(ips.into_iter().map(|r| r.unwrap()).collect())
+}
+
+fn json_response<T: serde::Serialize>(
+    request_headers: &HeaderMap,
+    value: &T,
+) -> Response<BoxBody<Bytes, BoxError>> {
+    let json = serde_json::to_string(value);
+
+    let mut response = Response::builder()
+        .status(StatusCode::OK)
+        .body(make_body(
+            json.unwrap_or("Error serializing response".to_string()),
+        ))
+        .unwrap();
+    cors(request_headers, &mut response, ALLOW_ORIGINS);
+    response
+}
+
 async fn geoip_lookup(
     app: &AppHandle,
     req: Request<Incoming>,
-) -> hyper::Result<Response<BoxBody<Bytes, hyper::Error>>> {
+) -> hyper::Result<Response<BoxBody<Bytes, BoxError>>> {
     let headers = req.headers().clone();
     let request_body = req.collect().await?.to_bytes();
-    let request_bytes = request_body.as_ref();
-
-    match serde_json::from_slice::<Vec<String>>(request_bytes) {
-        Ok(ipstr) => {
-            let ips: Vec<_> = ipstr.iter().map(|ip| ip.parse::<IpAddr>()).collect();
-            let error_ips: Vec<_> = ips
-                .iter()
-                .enumerate()
-                .filter(|(_, ip)| ip.is_err())
-                .map(|(i, r)| (i, r.clone().err().unwrap()))
-                .collect();
-            if !error_ips.is_empty() {
-                let msgs: Vec<_> = error_ips
-                    .into_iter()
-                    .map(|(i, e)| format!("{}: {}", ipstr[i], e))
-                    .collect();
-                let msg = format!("{}\n", msgs.join("\n"));
-                return Ok(invalid_request(&headers, msg.as_str()));
-            }
 
-            let good_ips = ips.into_iter().map(|r| r.unwrap()).collect();
+    match parse_ips(request_body.as_ref()) {
+        Ok(ips) => Ok(json_response(
+            &headers,
+            &crate::geoip::lookup(app, ips).await,
+        )),
+        Err(msg) => Ok(invalid_request(&headers, msg.as_str())),
+    }
+}
 
-            let lookup_response = serde_json::to_string(&crate::geoip::lookup(app, good_ips).await);
+async fn rdns_lookup(
+    app: &AppHandle,
+    req: Request<Incoming>,
+) -> hyper::Result<Response<BoxBody<Bytes, BoxError>>> {
+    let headers = req.headers().clone();
+    let request_body = req.collect().await?.to_bytes();
 
-            let mut response = Response::builder()
-                .status(StatusCode::OK)
-                .body(make_body(
-                    lookup_response.unwrap_or("Error serializing response".to_string()),
-                ))
-                .unwrap();
-            cors(&headers, &mut response, ALLOW_ORIGINS);
-            Ok(response)
+    match parse_ips(request_body.as_ref()) {
+        Ok(ips) => {
+            let rdns = app.state::<ReverseDns>();
+            Ok(json_response(&headers, &rdns.lookup(ips).await))
         }
-        Err(e) => Ok(invalid_request(
-            &headers,
-            format!("Can not parse json: {e}\n").as_str(),
-        )),
+        Err(msg) => Ok(invalid_request(&headers, msg.as_str())),
     }
 }
 
@@ -208,7 +285,7 @@ async fn proxy_fetch(
     app: &AppHandle,
     req: Request<Incoming>,
     process: bool,
-) -> hyper::Result<Response<BoxBody<Bytes, hyper::Error>>> {
+) -> hyper::Result<Response<BoxBody<Bytes, BoxError>>> {
     let req_headers = req.headers().clone();
     let toast = req_headers.get("X-TrguiNG-Toast").is_some();
     let sound = req_headers.get("X-TrguiNG-Sound").is_some();
@@ -227,10 +304,39 @@ async fn proxy_fetch(
 
         let insecure = params.iter().any(|p| p.0 == "insecure" && p.1 == "true");
 
-        if let Some(url) = params.iter().find_map(|p| if p.0 == "url" { Some(p.1) } else { None })
+        // Connection specific client settings are passed as url encoded json in a
+        // header to keep proxy and certificate passwords out of the url, the older
+        // insecure flag is still accepted when they are missing.
+        let client_header = req
+            .headers()
+            .get(TRGUING_CLIENT)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        let client_settings = match client_header {
+            Some(settings) => match urlencoding::decode(&settings)
+                .map_err(|e| e.to_string())
+                .and_then(|s| serde_json::from_str::<ClientSettings>(&s).map_err(|e| e.to_string()))
+            {
+                Ok(settings) => settings,
+                Err(e) => {
+                    return Ok(invalid_request(
+                        req.headers(),
+                        format!("bad client settings: {e}").as_str(),
+                    ))
+                }
+            },
+            None if insecure => ClientSettings::insecure(),
+            None => ClientSettings::default(),
+        };
+
+        if let Some(url) = params
+            .iter()
+            .find_map(|p| if p.0 == "url" { Some(p.1) } else { None })
         {
-            let clients = app.state::<crate::HttpClients>();
-            let client = if insecure { &clients.insecure } else { &clients.default };
+            let client = match app.state::<HttpClients>().get(&client_settings) {
+                Ok(client) => client,
+                Err(e) => return Ok(invalid_request(req.headers(), e.as_str())),
+            };
 
             let url = urlencoding::decode(url).ok().unwrap().into_owned();
             let headers = req.headers().clone();
@@ -238,10 +344,6 @@ async fn proxy_fetch(
                 .post(url.clone())
                 .header(reqwest::header::CONTENT_TYPE, "application/json");
 
-            if let Some(tr_header) = headers.get(TRANSMISSION_SESSION_ID) {
-                req_builder =
-                    req_builder.header(TRANSMISSION_SESSION_ID, tr_header.to_str().unwrap());
-            }
             if let Some(auth_header) = headers.get(AUTHORIZATION) {
                 req_builder = req_builder.header(
                     reqwest::header::AUTHORIZATION,
@@ -251,27 +353,53 @@ async fn proxy_fetch(
 
             let req_body = req.collect().await?.to_bytes();
 
-            match req_builder.body(reqwest::Body::from(req_body)).send().await {
+            // Session id is managed by the backend, session id sent by the frontend is ignored
+            let sessions = app.state::<TransmissionSessions>();
+            match sessions
+                .send(&url, req_builder.body(reqwest::Body::from(req_body)))
+                .await
+            {
                 Ok(response) => {
                     let is_ok = response.status().is_success();
-                    let hyper_response = convert_response(&response);
+                    let mut hyper_response = convert_response(&response);
 
-                    let response_bytes = response.bytes().await;
-                    if response_bytes.is_err() {
-                        return Ok(fetch_error(&req_headers));
+                    let encoder = if response
+                        .content_length()
+                        .is_some_and(|len| len < MIN_COMPRESS_SIZE)
+                    {
+                        None
+                    } else {
+                        ContentEncoder::from_request(&headers)
+                    };
+                    if let Some(encoder) = &encoder {
+                        if let Some(response_headers) = hyper_response.headers_mut() {
+                            response_headers.remove(CONTENT_LENGTH);
+                            response_headers
+                                .insert(CONTENT_ENCODING, HeaderValue::from_static(encoder.name()));
+                            response_headers
+                                .append(VARY, HeaderValue::from_static("Accept-Encoding"));
+                        }
                     }
-                    let response_bytes = response_bytes.unwrap();
 
-                    if is_ok && process {
-                        let _ = process_response(app, &response_bytes, url.as_str(), toast, sound)
-                            .await;
-                    }
-                    let mut hyper_response =
-                        hyper_response.body(make_body(response_bytes)).unwrap();
+                    // Response is streamed to the webview as it arrives while a copy of
+                    // every chunk is fed to the torrent cache parser.
+                    let (body_tx, body) = Channel::<Bytes, BoxError>::new(PROXY_BODY_CHANNEL_SIZE);
+                    let parser_tx = if is_ok && process {
+                        Some(spawn_response_parser(app, url, toast, sound))
+                    } else {
+                        None
+                    };
+
+                    async_runtime::spawn(stream_response(response, body_tx, parser_tx, encoder));
+
+                    let mut hyper_response = hyper_response.body(body.boxed()).unwrap();
                     cors(&headers, &mut hyper_response, ALLOW_ORIGINS);
                     Ok(hyper_response)
                 }
                 Err(e) => {
+                    if process {
+                        crate::status::set_disconnected(app, &url);
+                    }
                     if e.is_timeout()
                         || e.source().is_some_and(|s| {
                             s.downcast_ref::<io::Error>()
@@ -279,11 +407,14 @@ async fn proxy_fetch(
                         })
                     {
                         Ok(timed_out(&headers))
+                    } else if e.is_connect() {
+                        Ok(connection_failed(&headers, error_chain(&e).as_str()))
                     } else {
-                        Ok(Response::builder()
-                            .status(e.status().unwrap_or(StatusCode::SERVICE_UNAVAILABLE))
-                            .body(make_body(e.to_string()))
-                            .unwrap())
+                        Ok(fetch_error(
+                            &headers,
+                            e.status().unwrap_or(StatusCode::SERVICE_UNAVAILABLE),
+                            error_chain(&e).as_str(),
+                        ))
                     }
                 }
             }
@@ -295,6 +426,65 @@ async fn proxy_fetch(
     }
 }
 
+// Failures abort the body so that the webview sees a broken response instead
+// of a truncated one that looks complete.
+async fn stream_response(
+    mut response: reqwest::Response,
+    mut body_tx: Sender<Bytes, BoxError>,
+    mut parser_tx: Option<mpsc::Sender<Bytes>>,
+    mut encoder: Option<ContentEncoder>,
+) {
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                if let Some(tx) = &parser_tx {
+                    match tokio::time::timeout(PARSER_SEND_TIMEOUT, tx.send(chunk.clone())).await {
+                        Ok(Ok(())) => {}
+                        // Parser stopped, e.g. on invalid json
+                        Ok(Err(_)) => parser_tx = None,
+                        Err(_) => {
+                            println!("Torrent cache parser fell behind, response is not cached");
+                            parser_tx = None;
+                        }
+                    }
+                }
+                let chunk = match &mut encoder {
+                    Some(encoder) => match encoder.encode(&chunk).await {
+                        Ok(encoded) => encoded,
+                        Err(e) => {
+                            println!("Error compressing proxied response: {e}");
+                            body_tx.abort(e.into());
+                            return;
+                        }
+                    },
+                    None => chunk,
+                };
+                if !chunk.is_empty() && body_tx.send_data(chunk).await.is_err() {
+                    return;
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                println!("Error reading proxied response: {e}");
+                body_tx.abort(e.into());
+                return;
+            }
+        }
+    }
+
+    if let Some(encoder) = &mut encoder {
+        match encoder.finish().await {
+            Ok(encoded) => {
+                body_tx.send_data(encoded).await.ok();
+            }
+            Err(e) => {
+                println!("Error compressing proxied response: {e}");
+                body_tx.abort(e.into());
+            }
+        }
+    }
+}
+
 fn convert_response(response: &reqwest::Response) -> tauri::http::response::Builder {
     let mut hyper_response = hyper::Response::builder()
         .status(response.status().as_u16())
@@ -315,6 +505,65 @@ fn convert_response(response: &reqwest::Response) -> tauri::http::response::Buil
     hyper_response
 }
 
+// Torrents and options passed on the command line to the running instance
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AddArgs {
+    pub torrents: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    #[serde(flatten)]
+    pub options: AddOptions,
+    // Add directly from the backend instead of opening the add dialog
+    #[serde(default)]
+    pub no_dialog: bool,
+}
+
+// Returns error only when the torrents could not be added at all, in which case
+// they are passed to the frontend as usual.
+pub async fn add_without_dialog(app: &AppHandle, args: &AddArgs) -> Result<(), String> {
+    let config = AppConfig::load(app)?;
+    let connection = config
+        .server_connection(args.server.as_deref())
+        .ok_or_else(|| {
+            format!(
+                "Server {:?} not found",
+                args.server.as_deref().unwrap_or("")
+            )
+        })?;
+    let clients = app.state::<HttpClients>();
+    let sessions = app.state::<TransmissionSessions>();
+    let rpc = Rpc::new(&clients, &sessions, &connection).with_config(&config);
+
+    for torrent in args.torrents.iter() {
+        match rpc.add_torrent(torrent, &args.options).await {
+            Ok(result) => {
+                let title = if result.duplicate {
+                    "Torrent already exists"
+                } else {
+                    "Torrent added"
+                };
+                show_notification(app, title, &result.name);
+                let is_file = !torrent.contains("://") && !torrent.starts_with("magnet:");
+                if is_file && !result.duplicate && config.delete_added() {
+                    if let Err(e) = crate::commands::remove_file(torrent.clone(), None).await {
+                        println!("{e}");
+                    }
+                }
+            }
+            Err(e) => show_notification(app, "Unable to add torrent", &format!("{torrent}: {e}")),
+        }
+    }
+
+    Ok(())
+}
+
+pub fn show_notification(app: &AppHandle, title: &str, body: &str) {
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        println!("Cannot show notification: {e:?}");
+    }
+}
+
 async fn send_payload(app: &AppHandle, payload: Bytes) {
     app.get_webview_window("main")
         .unwrap()
@@ -441,7 +690,7 @@ impl Ipc {
         self.listening = false;
     }
 
-    pub async fn send(&self, args: &Vec<String>, app: AppHandle) -> Result<(), String> {
+    pub async fn send(&self, args: &AddArgs, app: AppHandle) -> Result<(), String> {
         let client = reqwest::Client::builder()
             .connect_timeout(Duration::from_secs(3))
             .read_timeout(Duration::from_secs(10))
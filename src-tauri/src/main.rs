@@ -19,27 +19,50 @@
     windows_subsystem = "windows"
 )]
 
-use std::{sync::Arc, time::Duration};
+use std::sync::Arc;
 
+use appconfig::AppConfig;
+use clipboard::ClipboardWatchHandle;
 use createtorrent::CreationRequestsHandle;
 use geoip::MmdbReaderHandle;
+use httpclient::HttpClients;
+use ipc::AddArgs;
 use poller::PollerHandle;
+use rdns::ReverseDns;
+use rss::RssHandle;
+use session::TransmissionSessions;
+use status::TransferStatusHandle;
 use tauri::{async_runtime, App, AppHandle, Listener, Manager, State};
 use tauri_plugin_cli::CliExt;
 use tokio::sync::RwLock;
-use torrentcache::TorrentCacheHandle;
+use torrentcache::{RecentCompletedHandle, TorrentCacheHandle};
+use tray::TrayMenuHandle;
+use watch::WatchFoldersHandle;
 
+mod actions;
+mod appconfig;
+mod cli;
+mod clipboard;
 mod commands;
+mod compression;
 mod createtorrent;
 mod geoip;
+mod httpclient;
 mod integrations;
 mod ipc;
 #[cfg(target_os = "macos")]
 mod macos;
 mod poller;
+mod rdns;
+mod rpc;
+mod rss;
+mod session;
 mod sound;
+mod status;
 mod torrentcache;
 mod tray;
+mod util;
+mod watch;
 
 struct ListenerHandle(Arc<RwLock<ipc::Ipc>>);
 
@@ -50,12 +73,57 @@ fn handle_uris(app: &AppHandle, uris: Vec<String>) {
     let app_handle = app.clone();
     async_runtime::spawn(async move {
         let listener = listener_lock.read().await;
-        if let Err(e) = listener.send(&uris, app_handle).await {
+        let args = AddArgs {
+            torrents: uris,
+            ..Default::default()
+        };
+        if let Err(e) = listener.send(&args, app_handle).await {
             println!("Unable to send args to listener: {:?}", e);
         }
     });
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum StartMode {
+    Normal,
+    Minimized,
+    // Only the tray icon and the poller are started, the window is created when opened from the tray
+    Tray,
+}
+
+async fn start(app: &AppHandle, mode: StartMode) {
+    let config = AppConfig::load(app).unwrap_or_else(|e| {
+        println!("{e}");
+        AppConfig::default()
+    });
+    // Watch folders, clipboard monitoring and rss feeds don't depend on the window
+    watch::set_folders(app, config.watch_folders());
+    clipboard::set_enabled(app, config.clipboard_monitoring());
+    rss::set_config(app, config.rss());
+    app.state::<TransferStatusHandle>()
+        .set_names(config.server_names());
+
+    // Without the tray icon there would be no way to bring up the window
+    let mode = if mode == StartMode::Tray && !config.show_tray_icon() {
+        StartMode::Minimized
+    } else {
+        mode
+    };
+    if mode != StartMode::Tray {
+        tray::create_main_window(app, mode == StartMode::Minimized);
+        return;
+    }
+
+    if app.tray_by_id(tray::TRAY_ID).is_none() {
+        tray::create_tray(app.clone());
+    }
+    tray::set_tray_showhide_text(app, "Show");
+
+    let poller_state: State<PollerHandle> = app.state();
+    let mut poller = poller_state.0.lock().await;
+    poller.set_configs(config.poller_configs(), config.toast(), config.sound());
+}
+
 fn setup(app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
     #[cfg(target_os = "macos")]
     {
@@ -67,24 +135,33 @@ fn setup(app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
         macos::listen_url();
     }
 
-    let mut torrents: Vec<String> = vec![];
+    let mut args = AddArgs::default();
+    let mut start_mode = StartMode::Normal;
     match app.cli().matches() {
         Ok(matches) => {
             if matches.args.contains_key("help") {
+                cli::attach_console();
                 println!("{}", matches.args["help"].value.as_str().unwrap());
                 app.handle().exit(0);
                 return Ok(());
             }
 
-            if matches.args["torrent"].value.is_array() {
-                torrents = matches.args["torrent"]
-                    .value
-                    .as_array()
-                    .unwrap()
-                    .iter()
-                    .map(|v| v.as_str().unwrap().to_string())
-                    .collect();
+            if let Some(subcommand) = &matches.subcommand {
+                let code = cli::run(app.handle(), subcommand);
+                app.handle().cleanup_before_exit();
+                std::process::exit(code);
             }
+
+            args.torrents = cli::values(&matches, "torrent");
+            if cli::flag(&matches, "tray") {
+                start_mode = StartMode::Tray;
+            } else if cli::flag(&matches, "minimized") {
+                start_mode = StartMode::Minimized;
+            }
+
+            args.server = cli::value(&matches, "server");
+            args.options = cli::add_options(&matches);
+            args.no_dialog = cli::flag(&matches, "no-dialog");
         }
         Err(_) => {
             println!("Unable to read cli args");
@@ -96,8 +173,7 @@ fn setup(app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
 
     async_runtime::spawn(async move {
         let poller_state: State<PollerHandle> = app.state();
-        let mut poller = poller_state.0.lock().await;
-        poller.set_app_handle(&app);
+        poller_state.0.lock().await.set_app_handle(&app);
 
         let listener_state: State<ListenerHandle> = app.state();
         let listener_lock = listener_state.0.clone();
@@ -123,15 +199,19 @@ fn setup(app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
                     listener.pause().await;
                 });
             });
-            tray::toggle_main_window(&app, None);
+            start(&app, start_mode).await;
         }
         drop(listener);
 
         let app_clone = app.clone();
         async_runtime::spawn(async move {
             let listener = listener_lock.read().await;
-            if let Err(e) = listener.send(&torrents, app_clone).await {
-                println!("Unable to send args to listener: {e}");
+            // Second instance always passes its args and exits, the first one only
+            // sends torrents to itself.
+            if !args.torrents.is_empty() || !listener.listening {
+                if let Err(e) = listener.send(&args, app_clone).await {
+                    println!("Unable to send args to listener: {e}");
+                }
             }
 
             #[cfg(target_os = "macos")]
@@ -154,42 +234,17 @@ fn setup(app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
         let app_clone = app.clone();
         app.listen("window-hidden", move |_| {
             tray::set_tray_showhide_text(&app_clone, "Show");
+        });
+
+        let app_clone = app.clone();
+        app.listen("window-unload", move |_| {
+            tray::unload_main_window(&app_clone);
         })
     });
 
     Ok(())
 }
 
-static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
-
-pub struct HttpClients {
-    pub default: reqwest::Client,
-    pub insecure: reqwest::Client,
-}
-
-fn client_builder() -> reqwest::ClientBuilder {
-    reqwest::Client::builder()
-        .user_agent(APP_USER_AGENT)
-        .connect_timeout(Duration::from_secs(10))
-        .read_timeout(Duration::from_secs(40))
-        .timeout(Duration::from_secs(60))
-        .http1_only()
-        .http1_title_case_headers()
-}
-
-fn http_clients() -> HttpClients {
-    let default = client_builder()
-        .build()
-        .expect("Failed to initialize http client");
-
-    let insecure = client_builder()
-        .danger_accept_invalid_certs(true)
-        .build()
-        .expect("Failed to initialize insecure http client");
-
-    HttpClients { default, insecure }
-}
-
 fn main() {
     let context = tauri::generate_context!();
 
@@ -202,10 +257,21 @@ fn main() {
         .plugin(tauri_plugin_clipboard_manager::init())
         .invoke_handler(tauri::generate_handler![
             commands::read_file,
+            commands::read_files,
+            commands::fetch_torrent,
             commands::remove_file,
+            commands::set_watch_folders,
+            commands::set_clipboard_monitoring,
+            commands::set_rss_config,
+            commands::test_rss_feed,
+            commands::rss_history,
+            commands::clear_rss_history,
             commands::shell_open,
             commands::set_poller_config,
             commands::app_integration,
+            commands::set_geoip_config,
+            commands::geoip_info,
+            commands::update_geoip_db,
             commands::get_file_stats,
             commands::create_torrent,
             commands::check_create_torrent,
@@ -221,8 +287,16 @@ fn main() {
         .manage(TorrentCacheHandle::default())
         .manage(PollerHandle::default())
         .manage(MmdbReaderHandle::default())
+        .manage(ReverseDns::default())
         .manage(CreationRequestsHandle::default())
-        .manage(http_clients())
+        .manage(HttpClients::default())
+        .manage(TransmissionSessions::default())
+        .manage(TransferStatusHandle::default())
+        .manage(TrayMenuHandle::default())
+        .manage(RecentCompletedHandle::default())
+        .manage(WatchFoldersHandle::default())
+        .manage(ClipboardWatchHandle::default())
+        .manage(RssHandle::default())
         .setup(setup);
 
     #[cfg(target_os = "macos")]
@@ -18,20 +18,23 @@ use serde::Deserialize;
 use std::{collections::HashMap, sync::Arc, time::Duration};
 use tauri::{
     async_runtime::{self, JoinHandle, Mutex},
-    http::HeaderValue,
     AppHandle, Manager, State,
 };
 
+use crate::appconfig::AppConfig;
+use crate::httpclient::{ClientSettings, HttpClients};
+use crate::session::TransmissionSessions;
+use crate::status::{self, TransferStatusHandle};
 use crate::torrentcache::process_response;
 
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
-struct Connection {
+pub struct Connection {
     url: String,
     username: String,
     password: String,
-    #[serde(default)]
-    accept_invalid_certs: bool,
+    #[serde(flatten)]
+    client: ClientSettings,
 }
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct PollerConfig {
@@ -40,10 +43,56 @@ pub struct PollerConfig {
     interval: u64,
 }
 
+impl Connection {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+
+    pub fn map_passwords(&mut self, map: impl Fn(&str) -> String) {
+        self.password = map(&self.password);
+        self.client.map_passwords(map);
+    }
+
+    // Torrent files for the server are downloaded through its proxy
+    pub fn download_settings(&self) -> ClientSettings {
+        self.client.for_download()
+    }
+
+    // Settings of all http clients used for the server
+    pub fn client_settings(&self) -> [ClientSettings; 2] {
+        [self.client.clone(), self.download_settings()]
+    }
+
+    // Rpc request to the server with authentication, the caller adds the body
+    pub fn request(&self, clients: &HttpClients) -> Result<reqwest::RequestBuilder, String> {
+        let client = clients.get(&self.client)?;
+        let mut req = client
+            .post(self.url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+        if !self.username.is_empty() || !self.password.is_empty() {
+            req = req.basic_auth(&self.username, Some(&self.password));
+        }
+        Ok(req)
+    }
+}
+
+impl PollerConfig {
+    pub fn new(name: String, connection: Connection, interval: u64) -> Self {
+        Self {
+            name,
+            connection,
+            interval,
+        }
+    }
+}
+
 #[derive(Debug)]
 struct PollerConfigData {
     config: PollerConfig,
-    transmission_session: Option<String>,
     join_handle: JoinHandle<()>,
 }
 
@@ -72,6 +121,26 @@ impl Poller {
         configs.drain(..).for_each(|c| {
             self.add_config(c);
         });
+
+        if let Some(app) = &self.app_handle {
+            app.state::<TransferStatusHandle>().set_names(
+                self.configs
+                    .values()
+                    .map(|c| (c.config.connection.url.clone(), c.config.name.clone())),
+            );
+            crate::tray::set_servers(app, self.configs.keys().cloned().collect());
+
+            // Called when servers are edited, clients of old settings are not needed anymore
+            let mut used: Vec<ClientSettings> = self
+                .configs
+                .values()
+                .flat_map(|c| c.config.connection.client_settings())
+                .collect();
+            if let Ok(config) = AppConfig::load(app) {
+                used.extend(config.client_settings());
+            }
+            app.state::<HttpClients>().retain(&used);
+        }
     }
 
     fn add_config(&mut self, config: PollerConfig) {
@@ -86,7 +155,6 @@ impl Poller {
                 config.name.clone(),
                 PollerConfigData {
                     config,
-                    transmission_session: None,
                     join_handle,
                 },
             );
@@ -112,103 +180,68 @@ async fn polling_task(app: AppHandle, name: String, toast: bool, sound: bool) {
 
         // Acquire lock only to get copies of data needed for polling
         let poller = poller_handle.0.lock().await;
-        let data = poller.configs.get(&name).unwrap();
-        let connection = data.config.connection.clone();
-        let session = data.transmission_session.clone();
+        let connection = poller.configs.get(&name).unwrap().config.connection.clone();
         drop(poller);
 
-        let result = poll(&app.clone(), connection.clone(), session, toast, sound).await;
-        let mut new_session = None;
-
-        match result {
-            Ok(_) => {}
-            Err(Some(session)) => {
-                // try again with new session token
-                if (poll(&app, connection, Some(session.clone()), toast, sound).await).is_ok() {
-                    new_session = Some(session);
-                }
-            }
-            Err(None) => {}
-        }
-        if let Some(session) = new_session {
-            // Reaquire lock to write session token
-            let mut poller = poller_handle.0.lock().await;
-            let data = poller.configs.get_mut(&name).unwrap();
-            data.transmission_session = Some(session);
-        };
+        poll(&app, connection, toast, sound).await;
     }
 }
 
-const TRANSMISSION_SESSION: &str = "X-Transmission-Session-Id";
 const TORRENT_GET_BODY: &str = r#"
 {
     "method": "torrent-get",
     "arguments": {
-        "fields": ["id","name","status"]
+        "fields": ["id","name","status","rateDownload","rateUpload","error","downloadDir","hashString"]
     }
 }"#;
 
-async fn poll(
-    app: &AppHandle,
-    connection: Connection,
-    session: Option<String>,
-    toast: bool,
-    sound: bool,
-) -> Result<String, Option<String>> {
-    let clients = app.state::<crate::HttpClients>();
-    let client = if connection.accept_invalid_certs { &clients.insecure } else { &clients.default };
-
-    let mut req = client
-        .post(connection.url.clone())
-        .header(reqwest::header::CONTENT_TYPE, "application/json");
-    if let Some(session) = session {
-        req = req.header(
-            TRANSMISSION_SESSION,
-            HeaderValue::from_str(session.as_str()).unwrap(),
-        );
-    }
-    if !connection.username.is_empty() || !connection.password.is_empty() {
-        req = req.basic_auth(connection.username, Some(connection.password));
-    }
-
-    match req.body(reqwest::Body::from(TORRENT_GET_BODY)).send().await {
+async fn poll(app: &AppHandle, connection: Connection, toast: bool, sound: bool) {
+    let req = match connection.request(&app.state::<HttpClients>()) {
+        Ok(req) => req,
+        Err(e) => {
+            println!("Error during polling: {e}");
+            status::set_disconnected(app, &connection.url);
+            return;
+        }
+    };
+
+    let sessions = app.state::<TransmissionSessions>();
+    match sessions
+        .send(
+            &connection.url,
+            req.body(reqwest::Body::from(TORRENT_GET_BODY)),
+        )
+        .await
+    {
         Ok(response) => {
-            let session = response
-                .headers()
-                .get(TRANSMISSION_SESSION)
-                .map(|v| v.to_owned());
-            if response.status().is_success() || response.status().as_u16() == 409 {
-                let session_str = session.map_or(String::default(), |f| {
-                    f.to_str().unwrap_or_default().to_string()
-                });
-                if response.status().is_success() {
-                    let response_bytes = response
-                        .bytes()
-                        .await
-                        .map_err(|_| "Failed to read response".to_string())?;
-                    let _ = process_response(
-                        app,
-                        &response_bytes,
-                        connection.url.as_str(),
-                        toast,
-                        sound,
-                    )
-                    .await;
-                    Ok(session_str)
-                } else {
-                    Err(Some(session_str))
+            if response.status().is_success() {
+                match response.bytes().await {
+                    Ok(response_bytes) => {
+                        let _ = process_response(
+                            app,
+                            &response_bytes,
+                            connection.url.as_str(),
+                            toast,
+                            sound,
+                        )
+                        .await;
+                    }
+                    Err(e) => {
+                        println!("Failed to read response: {e:?}");
+                        status::set_disconnected(app, &connection.url);
+                    }
                 }
             } else {
                 println!(
                     "Unexpected status code during polling: {:?}",
                     response.status()
                 );
-                Err(None)
+                status::set_disconnected(app, &connection.url);
             }
         }
         Err(e) => {
             println!("Error during polling: {e:?}");
-            Err(None)
+            status::set_disconnected(app, &connection.url);
         }
     }
 }
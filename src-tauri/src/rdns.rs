@@ -0,0 +1,276 @@
+// TrguiNG - next gen remote GUI for transmission torrent daemon
+// Copyright (C) 2023  qu1ck (mail at qu1ck.org)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use lru::LruCache;
+use tauri::async_runtime;
+use tokio::sync::Semaphore;
+
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(3);
+const CACHE_SIZE: usize = 4096;
+// Failed lookups are retried sooner than successful ones are refreshed
+const CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+const NEGATIVE_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+// Every lookup blocks a thread of the shared blocking pool
+const MAX_CONCURRENT_LOOKUPS: usize = 8;
+
+// Resolves an address to a host name, blocking the calling thread.
+// Implemented by the system resolver and by stand-ins that don't need a network.
+pub trait ReverseResolver: Send + Sync {
+    fn resolve(&self, ip: IpAddr) -> Option<String>;
+}
+
+pub struct SystemResolver;
+
+impl ReverseResolver for SystemResolver {
+    fn resolve(&self, ip: IpAddr) -> Option<String> {
+        // getnameinfo returns the numeric address when there is no PTR record
+        dns_lookup::lookup_addr(&ip)
+            .ok()
+            .filter(|name| name.parse::<IpAddr>().is_err())
+    }
+}
+
+// Fixed address to name map, useful as a local resolver stand-in
+impl ReverseResolver for HashMap<IpAddr, String> {
+    fn resolve(&self, ip: IpAddr) -> Option<String> {
+        self.get(&ip).cloned()
+    }
+}
+
+#[derive(serde::Serialize, Clone)]
+pub struct RdnsResult {
+    ip: IpAddr,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hostname: Option<String>,
+}
+
+struct CacheEntry {
+    hostname: Option<String>,
+    expires: Instant,
+}
+
+struct RdnsState {
+    cache: LruCache<IpAddr, CacheEntry>,
+    pending: HashSet<IpAddr>,
+}
+
+// Clears the pending flag when the lookup ends, also if the resolver panics
+struct PendingGuard {
+    state: Arc<Mutex<RdnsState>>,
+    ip: IpAddr,
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.pending.remove(&self.ip);
+    }
+}
+
+pub struct ReverseDns {
+    resolver: Arc<dyn ReverseResolver>,
+    state: Arc<Mutex<RdnsState>>,
+    lookups: Arc<Semaphore>,
+}
+
+impl Default for ReverseDns {
+    fn default() -> Self {
+        Self::new(Arc::new(SystemResolver))
+    }
+}
+
+impl ReverseDns {
+    pub fn new(resolver: Arc<dyn ReverseResolver>) -> Self {
+        Self {
+            resolver,
+            state: Arc::new(Mutex::new(RdnsState {
+                cache: LruCache::new(NonZeroUsize::new(CACHE_SIZE).unwrap()),
+                pending: HashSet::new(),
+            })),
+            lookups: Arc::new(Semaphore::new(MAX_CONCURRENT_LOOKUPS)),
+        }
+    }
+
+    // Returns host names of the given addresses. Addresses that could not be resolved
+    // within the timeout are left out of the result, their lookups keep running
+    // in the background and the result is cached for the next request.
+    pub async fn lookup(&self, ips: Vec<IpAddr>) -> Vec<RdnsResult> {
+        let mut results = vec![];
+        let mut tasks = vec![];
+        {
+            let mut state = self.state.lock().unwrap();
+            let now = Instant::now();
+            for ip in ips.into_iter().collect::<HashSet<_>>() {
+                match state.cache.get(&ip) {
+                    Some(entry) if entry.expires > now => results.push(RdnsResult {
+                        ip,
+                        hostname: entry.hostname.clone(),
+                    }),
+                    _ => {
+                        // Lookups started by another request are not waited for
+                        if state.pending.insert(ip) {
+                            tasks.push((ip, self.spawn_resolve(ip)));
+                        }
+                    }
+                }
+            }
+        }
+
+        let deadline = tokio::time::Instant::now() + RESOLVE_TIMEOUT;
+        for (ip, task) in tasks {
+            if let Ok(Ok(hostname)) = tokio::time::timeout_at(deadline, task).await {
+                results.push(RdnsResult { ip, hostname });
+            }
+        }
+
+        results
+    }
+
+    fn spawn_resolve(&self, ip: IpAddr) -> async_runtime::JoinHandle<Option<String>> {
+        let resolver = self.resolver.clone();
+        let state = self.state.clone();
+        let lookups = self.lookups.clone();
+        async_runtime::spawn(async move {
+            let _pending = PendingGuard {
+                state: state.clone(),
+                ip,
+            };
+            let _permit = lookups.acquire_owned().await.ok()?;
+            let hostname = async_runtime::spawn_blocking(move || resolver.resolve(ip))
+                .await
+                .ok()?;
+            let ttl = if hostname.is_some() {
+                CACHE_TTL
+            } else {
+                NEGATIVE_CACHE_TTL
+            };
+
+            state.lock().unwrap().cache.put(
+                ip,
+                CacheEntry {
+                    hostname: hostname.clone(),
+                    expires: Instant::now() + ttl,
+                },
+            );
+            hostname
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    // Counts the lookups that reach the resolver
+    #[derive(Default)]
+    struct CountingResolver {
+        names: HashMap<IpAddr, String>,
+        calls: AtomicUsize,
+    }
+
+    impl ReverseResolver for CountingResolver {
+        fn resolve(&self, ip: IpAddr) -> Option<String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            self.names.resolve(ip)
+        }
+    }
+
+    struct PanickingResolver;
+
+    impl ReverseResolver for PanickingResolver {
+        fn resolve(&self, _ip: IpAddr) -> Option<String> {
+            panic!("resolver failed");
+        }
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn hostnames(mut results: Vec<RdnsResult>) -> Vec<(IpAddr, Option<String>)> {
+        results.sort_by_key(|r| r.ip);
+        results.into_iter().map(|r| (r.ip, r.hostname)).collect()
+    }
+
+    #[test]
+    fn caches_hits_and_misses() {
+        let resolver = Arc::new(CountingResolver {
+            names: HashMap::from([(ip("10.0.0.1"), "one.example".to_string())]),
+            ..Default::default()
+        });
+        let rdns = ReverseDns::new(resolver.clone());
+        let expected = vec![
+            (ip("10.0.0.1"), Some("one.example".to_string())),
+            (ip("10.0.0.2"), None),
+        ];
+
+        let first = async_runtime::block_on(rdns.lookup(vec![
+            ip("10.0.0.1"),
+            ip("10.0.0.2"),
+            ip("10.0.0.1"),
+        ]));
+        assert_eq!(hostnames(first), expected);
+        assert_eq!(resolver.calls.load(Ordering::SeqCst), 2);
+
+        let second = async_runtime::block_on(rdns.lookup(vec![ip("10.0.0.1"), ip("10.0.0.2")]));
+        assert_eq!(hostnames(second), expected);
+        assert_eq!(resolver.calls.load(Ordering::SeqCst), 2);
+        assert!(rdns.state.lock().unwrap().pending.is_empty());
+    }
+
+    #[test]
+    fn does_not_repeat_pending_lookups() {
+        let resolver = Arc::new(CountingResolver::default());
+        let rdns = ReverseDns::new(resolver.clone());
+        rdns.state.lock().unwrap().pending.insert(ip("10.0.0.3"));
+
+        let results = async_runtime::block_on(rdns.lookup(vec![ip("10.0.0.3")]));
+        assert!(results.is_empty());
+        assert_eq!(resolver.calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn resolves_more_addresses_than_concurrent_lookups() {
+        let names: HashMap<IpAddr, String> = (0..50u8)
+            .map(|i| (IpAddr::from([10, 0, 1, i]), format!("host{i}.example")))
+            .collect();
+        let rdns = ReverseDns::new(Arc::new(names));
+
+        let results = async_runtime::block_on(
+            rdns.lookup((0..50u8).map(|i| IpAddr::from([10, 0, 1, i])).collect()),
+        );
+        assert_eq!(results.len(), 50);
+        assert!(results.iter().all(|r| r.hostname.is_some()));
+    }
+
+    #[test]
+    fn clears_pending_when_resolver_panics() {
+        let rdns = ReverseDns::new(Arc::new(PanickingResolver));
+
+        async_runtime::block_on(rdns.lookup(vec![ip("10.0.0.4")]));
+        assert!(rdns.state.lock().unwrap().pending.is_empty());
+    }
+}
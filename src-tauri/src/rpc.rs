@@ -0,0 +1,175 @@
+// TrguiNG - next gen remote GUI for transmission torrent daemon
+// Copyright (C) 2023  qu1ck (mail at qu1ck.org)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::appconfig::AppConfig;
+use crate::httpclient::HttpClients;
+use crate::poller::Connection;
+use crate::session::TransmissionSessions;
+
+// Options for torrents added by the backend, unset values use server defaults
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AddOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_dir: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paused: Option<bool>,
+    // Bandwidth priority: -1 low, 0 normal, 1 high
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i8>,
+}
+
+pub struct AddResult {
+    pub name: String,
+    pub duplicate: bool,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    result: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+// Transmission rpc calls made by the backend itself, with the same http clients
+// and session ids that the poller and the proxy use.
+pub struct Rpc<'a> {
+    clients: &'a HttpClients,
+    sessions: &'a TransmissionSessions,
+    connection: &'a Connection,
+    config: Option<&'a AppConfig>,
+}
+
+impl<'a> Rpc<'a> {
+    pub fn new(
+        clients: &'a HttpClients,
+        sessions: &'a TransmissionSessions,
+        connection: &'a Connection,
+    ) -> Self {
+        Self {
+            clients,
+            sessions,
+            connection,
+            config: None,
+        }
+    }
+
+    // Torrent urls of sites with configured cookies or headers are downloaded
+    // by the backend instead of the server.
+    pub fn with_config(mut self, config: &'a AppConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    pub async fn call(&self, method: &str, arguments: Value) -> Result<Value, String> {
+        let body = json!({ "method": method, "arguments": arguments });
+        let req = self
+            .connection
+            .request(self.clients)?
+            .body(body.to_string());
+
+        let response = self
+            .sessions
+            .send(self.connection.url(), req)
+            .await
+            .map_err(|e| format!("Request to {} failed: {e}", self.connection.url()))?;
+        if !response.status().is_success() {
+            return Err(format!("Server returned error: {}", response.status()));
+        }
+
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| format!("Failed to read response: {e}"))?;
+        let response: RpcResponse = serde_json::from_slice(&bytes)
+            .map_err(|e| format!("Unable to parse server response: {e}"))?;
+        if response.result != "success" {
+            return Err(response.result);
+        }
+        Ok(response.arguments)
+    }
+
+    // Adds a magnet link, url or local .torrent file
+    pub async fn add_torrent(
+        &self,
+        torrent: &str,
+        options: &AddOptions,
+    ) -> Result<AddResult, String> {
+        let mut arguments = Map::new();
+        let is_url = torrent.starts_with("http://") || torrent.starts_with("https://");
+        let site_headers = self
+            .config
+            .filter(|_| is_url)
+            .map(|config| config.site_headers(torrent))
+            .unwrap_or_default();
+        if !site_headers.is_empty() {
+            let torrent = crate::commands::download_torrent(
+                self.clients,
+                &self.connection.download_settings(),
+                torrent,
+                site_headers,
+            )
+            .await?;
+            arguments.insert("metainfo".into(), torrent.metadata.into());
+        } else if torrent.starts_with("magnet:?") || is_url {
+            arguments.insert("filename".into(), torrent.into());
+        } else {
+            // Only file urls are percent encoded, plain paths may contain '%'
+            let path = match torrent.strip_prefix("file://") {
+                Some(path) => urlencoding::decode(path)
+                    .map(|p| p.into_owned())
+                    .unwrap_or(path.to_string()),
+                None => torrent.to_string(),
+            };
+            let torrent = crate::commands::read_file(path).await?;
+            arguments.insert("metainfo".into(), torrent.metadata.into());
+        }
+        if let Some(download_dir) = &options.download_dir {
+            arguments.insert("download-dir".into(), download_dir.as_str().into());
+        }
+        if !options.labels.is_empty() {
+            arguments.insert("labels".into(), options.labels.clone().into());
+        }
+        if let Some(paused) = options.paused {
+            arguments.insert("paused".into(), paused.into());
+        }
+        if let Some(priority) = options.priority {
+            arguments.insert("bandwidthPriority".into(), priority.into());
+        }
+
+        let result = self.call("torrent-add", arguments.into()).await?;
+        let (added, duplicate) =
+            match (result.get("torrent-added"), result.get("torrent-duplicate")) {
+                (Some(added), _) => (added, false),
+                (None, Some(duplicate)) => (duplicate, true),
+                _ => return Err("Unexpected server response".to_string()),
+            };
+
+        Ok(AddResult {
+            name: added
+                .get("name")
+                .and_then(|n| n.as_str())
+                .unwrap_or(torrent)
+                .to_string(),
+            duplicate,
+        })
+    }
+}
@@ -0,0 +1,936 @@
+// TrguiNG - next gen remote GUI for transmission torrent daemon
+// Copyright (C) 2023  qu1ck (mail at qu1ck.org)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::PathBuf,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant, SystemTime},
+};
+
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use tauri::{
+    async_runtime::{self, JoinHandle},
+    AppHandle, Manager,
+};
+
+use crate::appconfig::AppConfig;
+use crate::httpclient::{read_limited, ClientSettings, HttpClients};
+use crate::ipc::show_notification;
+use crate::rpc::{AddOptions, Rpc};
+use crate::session::TransmissionSessions;
+use crate::util::write_atomic;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const MIN_FEED_INTERVAL_MINUTES: u64 = 5;
+const HISTORY_FILE: &str = "rss_history.json";
+// Feeds larger than this are rejected
+const MAX_FEED_SIZE: u64 = 10 * 1024 * 1024;
+// Oldest entries are dropped, feeds don't keep items around for this long
+const HISTORY_SIZE: usize = 5000;
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RssFeed {
+    pub enabled: bool,
+    pub name: String,
+    pub url: String,
+    pub interval_minutes: u64,
+}
+
+impl Default for RssFeed {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            name: String::new(),
+            url: String::new(),
+            interval_minutes: 30,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RssRule {
+    pub enabled: bool,
+    pub name: String,
+    // Names of the feeds the rule applies to, all feeds when empty
+    pub feeds: Vec<String>,
+    // Case insensitive regular expressions matched against item titles
+    pub must_contain: String,
+    pub must_not_contain: String,
+    // Seasons and episodes to download, e.g. "1x1-12;2x1-", empty for any
+    pub episodes: String,
+    // Empty for the active server
+    pub server: String,
+    pub download_dir: String,
+    pub labels: Vec<String>,
+    pub paused: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct RssConfig {
+    pub feeds: Vec<RssFeed>,
+    pub rules: Vec<RssRule>,
+}
+
+pub struct FeedItem {
+    pub title: String,
+    // Magnet link or torrent url
+    pub link: String,
+    // Guid of the item or its link, identifies the item in the history
+    pub key: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Episode {
+    season: u32,
+    episode: u32,
+}
+
+impl std::fmt::Display for Episode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "S{:02}E{:02}", self.season, self.episode)
+    }
+}
+
+// Episodes first..=last of a season, last None means all following episodes
+struct EpisodeRange {
+    season: u32,
+    first: u32,
+    last: Option<u32>,
+}
+
+impl EpisodeRange {
+    fn contains(&self, episode: Episode) -> bool {
+        episode.season == self.season
+            && episode.episode >= self.first
+            && self.last.map_or(true, |last| episode.episode <= last)
+    }
+}
+
+// Parses "1x2;1x5-8;2x1-" style filters
+fn parse_episode_filter(filter: &str) -> Result<Vec<EpisodeRange>, String> {
+    filter
+        .split(';')
+        .map(|r| r.trim())
+        .filter(|r| !r.is_empty())
+        .map(|range| {
+            let invalid = || format!("Invalid episode filter {range:?}");
+            let (season, episodes) = range.split_once(['x', 'X']).ok_or_else(invalid)?;
+            let season = season.trim().parse::<u32>().map_err(|_| invalid())?;
+            let parse = |e: &str| e.trim().parse::<u32>().map_err(|_| invalid());
+            let (first, last) = match episodes.split_once('-') {
+                None => {
+                    let episode = parse(episodes)?;
+                    (episode, Some(episode))
+                }
+                Some((first, last)) if last.trim().is_empty() => (parse(first)?, None),
+                Some((first, last)) => (parse(first)?, Some(parse(last)?)),
+            };
+            Ok(EpisodeRange {
+                season,
+                first,
+                last,
+            })
+        })
+        .collect()
+}
+
+struct CompiledRule<'a> {
+    rule: &'a RssRule,
+    must_contain: Option<Regex>,
+    must_not_contain: Option<Regex>,
+    episodes: Vec<EpisodeRange>,
+}
+
+impl<'a> CompiledRule<'a> {
+    fn new(rule: &'a RssRule) -> Result<Self, String> {
+        let regex = |pattern: &str| {
+            if pattern.is_empty() {
+                return Ok(None);
+            }
+            RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .map(Some)
+                .map_err(|e| format!("Invalid pattern in rule {:?}: {e}", rule.name))
+        };
+        Ok(Self {
+            rule,
+            must_contain: regex(&rule.must_contain)?,
+            must_not_contain: regex(&rule.must_not_contain)?,
+            episodes: parse_episode_filter(&rule.episodes)?,
+        })
+    }
+
+    fn matches(&self, feed: &str, title: &str) -> bool {
+        (self.rule.feeds.is_empty() || self.rule.feeds.iter().any(|f| f == feed))
+            && self
+                .must_contain
+                .as_ref()
+                .map_or(true, |r| r.is_match(title))
+            && !self
+                .must_not_contain
+                .as_ref()
+                .is_some_and(|r| r.is_match(title))
+            && (self.episodes.is_empty()
+                || find_episode(title).is_some_and(|e| self.episodes.iter().any(|r| r.contains(e))))
+    }
+
+    fn options(&self) -> AddOptions {
+        AddOptions {
+            download_dir: Some(self.rule.download_dir.clone()).filter(|d| !d.is_empty()),
+            labels: self.rule.labels.clone(),
+            paused: Some(self.rule.paused),
+            priority: None,
+        }
+    }
+}
+
+fn compile_rules(rules: &[RssRule]) -> Vec<CompiledRule<'_>> {
+    rules
+        .iter()
+        .filter(|r| r.enabled)
+        .filter_map(|r| match CompiledRule::new(r) {
+            Ok(rule) => Some(rule),
+            Err(e) => {
+                println!("{e}");
+                None
+            }
+        })
+        .collect()
+}
+
+// First rule that matches the item and has not added its episode yet, rules without
+// an episode filter don't track episodes.
+fn select_rule<'r, 'a>(
+    rules: &'r [CompiledRule<'a>],
+    feed: &str,
+    title: &str,
+    has_episode: impl Fn(&str, &str) -> bool,
+) -> Option<(&'r CompiledRule<'a>, Option<String>)> {
+    rules
+        .iter()
+        .filter(|r| r.matches(feed, title))
+        .find_map(|rule| {
+            let episode = (!rule.episodes.is_empty())
+                .then(|| find_episode(title))
+                .flatten()
+                .map(|e| e.to_string());
+            match episode.as_deref() {
+                Some(episode) if has_episode(&rule.rule.name, episode) => None,
+                _ => Some((rule, episode)),
+            }
+        })
+}
+
+// Finds "S01E02" or "1x02" in the title
+fn find_episode(title: &str) -> Option<Episode> {
+    static EPISODE_REGEX: OnceLock<Regex> = OnceLock::new();
+    let regex = EPISODE_REGEX.get_or_init(|| {
+        Regex::new(r"(?i)\bS(\d{1,2})\s?E(\d{1,3})\b|\b(\d{1,2})x(\d{2,3})\b").unwrap()
+    });
+    let captures = regex.captures(title)?;
+    let number = |a: usize, b: usize| {
+        captures
+            .get(a)
+            .or_else(|| captures.get(b))
+            .and_then(|m| m.as_str().parse::<u32>().ok())
+    };
+    Some(Episode {
+        season: number(1, 3)?,
+        episode: number(2, 4)?,
+    })
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    key: String,
+    title: String,
+    feed: String,
+    rule: String,
+    // Unix time in seconds
+    added: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    episode: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+struct History {
+    entries: VecDeque<HistoryEntry>,
+}
+
+impl History {
+    fn contains(&self, key: &str) -> bool {
+        self.entries.iter().any(|e| e.key == key)
+    }
+
+    // Another release of the same episode was already added by the rule
+    fn has_episode(&self, rule: &str, episode: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|e| e.rule == rule && e.episode.as_deref() == Some(episode))
+    }
+
+    fn push(&mut self, entry: HistoryEntry) {
+        self.entries.push_back(entry);
+        while self.entries.len() > HISTORY_SIZE {
+            self.entries.pop_front();
+        }
+    }
+}
+
+fn history_path(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|p| p.join(HISTORY_FILE))
+        .map_err(|e| format!("Unable to resolve app data dir: {e}"))
+}
+
+fn load_history(app: &AppHandle) -> History {
+    let Ok(path) = history_path(app) else {
+        return History::default();
+    };
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return History::default(),
+        Err(e) => {
+            println!("Unable to read rss history {}: {e}", path.display());
+            return History::default();
+        }
+    };
+    serde_json::from_str(&text).unwrap_or_else(|e| {
+        println!("Unable to parse rss history {}: {e}", path.display());
+        History::default()
+    })
+}
+
+fn save_history(app: &AppHandle, history: &History) -> Result<(), String> {
+    let path = history_path(app)?;
+    let text = serde_json::to_string(history).unwrap();
+    write_atomic(&path, text.as_bytes()).map_err(|e| format!("Unable to save history: {e}"))
+}
+
+#[derive(Default)]
+struct RssState {
+    config: RssConfig,
+    task: Option<JoinHandle<()>>,
+    // Loaded on first use
+    history: Option<History>,
+}
+
+#[derive(Default)]
+pub struct RssHandle(Mutex<RssState>);
+
+impl RssHandle {
+    fn with_history<T>(&self, app: &AppHandle, f: impl FnOnce(&mut History) -> T) -> T {
+        // Loaded on first use, outside of the lock
+        let needs_load = self.0.lock().unwrap().history.is_none();
+        let loaded = needs_load.then(|| load_history(app));
+        let mut state = self.0.lock().unwrap();
+        f(state
+            .history
+            .get_or_insert_with(|| loaded.unwrap_or_default()))
+    }
+
+    // The history file is written without holding the lock
+    fn add_to_history(&self, app: &AppHandle, entry: HistoryEntry) {
+        let history = self.with_history(app, |h| {
+            h.push(entry);
+            h.clone()
+        });
+        if let Err(e) = save_history(app, &history) {
+            println!("{e}");
+        }
+    }
+}
+
+// Restarts the feed checker when the configuration changes
+pub fn set_config(app: &AppHandle, config: RssConfig) {
+    let handle = app.state::<RssHandle>();
+    let mut state = handle.0.lock().unwrap();
+    if state.config == config && state.task.is_some() {
+        return;
+    }
+
+    if let Some(task) = state.task.take() {
+        task.abort();
+    }
+    let has_feeds = config.feeds.iter().any(|f| f.enabled && !f.url.is_empty());
+    let has_rules = config.rules.iter().any(|r| r.enabled);
+    if has_feeds && has_rules {
+        state.task = Some(async_runtime::spawn(rss_task(app.clone(), config.clone())));
+    }
+    state.config = config;
+}
+
+pub fn history(app: &AppHandle) -> Vec<HistoryEntry> {
+    app.state::<RssHandle>().with_history(app, |history| {
+        history.entries.iter().rev().cloned().collect()
+    })
+}
+
+pub fn clear_history(app: &AppHandle) -> Result<(), String> {
+    let handle = app.state::<RssHandle>();
+    handle.0.lock().unwrap().history = Some(History::default());
+    save_history(app, &History::default())
+}
+
+async fn rss_task(app: AppHandle, config: RssConfig) {
+    let mut last_checked = HashMap::<String, Instant>::new();
+    // Items that could not be added are retried after a restart
+    let mut failed = HashSet::<String>::new();
+
+    loop {
+        for feed in config
+            .feeds
+            .iter()
+            .filter(|f| f.enabled && !f.url.is_empty())
+        {
+            let interval =
+                Duration::from_secs(feed.interval_minutes.max(MIN_FEED_INTERVAL_MINUTES) * 60);
+            if last_checked
+                .get(&feed.url)
+                .is_some_and(|checked| checked.elapsed() < interval)
+            {
+                continue;
+            }
+            last_checked.insert(feed.url.clone(), Instant::now());
+            if let Err(e) = check_feed(&app, feed, &config.rules, &mut failed).await {
+                println!("Unable to check feed {}: {e}", feed.name);
+            }
+        }
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+async fn check_feed(
+    app: &AppHandle,
+    feed: &RssFeed,
+    rules: &[RssRule],
+    failed: &mut HashSet<String>,
+) -> Result<(), String> {
+    let config = AppConfig::load(app)?;
+    let items = fetch_feed(&app.state::<HttpClients>(), &config, &feed.url).await?;
+    let rules = compile_rules(rules);
+    let handle = app.state::<RssHandle>();
+
+    // Oldest items first so that the earliest release of an episode wins
+    for item in items.iter().rev() {
+        if failed.contains(&item.key) || handle.with_history(app, |h| h.contains(&item.key)) {
+            continue;
+        }
+        let Some((rule, episode)) =
+            select_rule(&rules, &feed.name, &item.title, |rule, episode| {
+                handle.with_history(app, |h| h.has_episode(rule, episode))
+            })
+        else {
+            continue;
+        };
+
+        match add_item(app, &config, rule, item).await {
+            Ok(duplicate) => {
+                let title = if duplicate {
+                    "Torrent already exists"
+                } else {
+                    "Torrent added from RSS"
+                };
+                show_notification(app, title, &item.title);
+                let entry = HistoryEntry {
+                    key: item.key.clone(),
+                    title: item.title.clone(),
+                    feed: feed.name.clone(),
+                    rule: rule.rule.name.clone(),
+                    added: SystemTime::now()
+                        .duration_since(SystemTime::UNIX_EPOCH)
+                        .map_or(0, |d| d.as_secs()),
+                    episode,
+                };
+                handle.add_to_history(app, entry);
+            }
+            Err(e) => {
+                failed.insert(item.key.clone());
+                show_notification(
+                    app,
+                    "Unable to add torrent from RSS",
+                    &format!("{}: {e}", item.title),
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Returns whether the torrent already existed on the server
+async fn add_item(
+    app: &AppHandle,
+    config: &AppConfig,
+    rule: &CompiledRule<'_>,
+    item: &FeedItem,
+) -> Result<bool, String> {
+    let server = Some(rule.rule.server.as_str()).filter(|s| !s.is_empty());
+    let connection = config
+        .server_connection(server)
+        .ok_or_else(|| format!("Server {:?} not found", rule.rule.server))?;
+    let clients = app.state::<HttpClients>();
+    let sessions = app.state::<TransmissionSessions>();
+    Rpc::new(&clients, &sessions, &connection)
+        .with_config(config)
+        .add_torrent(&item.link, &rule.options())
+        .await
+        .map(|result| result.duplicate)
+}
+
+async fn fetch_feed(
+    clients: &HttpClients,
+    config: &AppConfig,
+    url: &str,
+) -> Result<Vec<FeedItem>, String> {
+    let mut request = clients.get(&ClientSettings::download())?.get(url);
+    for (name, value) in config.site_headers(url) {
+        request = request.header(name, value);
+    }
+    let response = request
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Unable to download {url}: {e}"))?;
+    let data = read_limited(response, MAX_FEED_SIZE)
+        .await
+        .map_err(|e| format!("Unable to download {url}: {e}"))?;
+    parse_feed(&String::from_utf8_lossy(&data))
+}
+
+#[derive(Default)]
+struct ItemBuilder {
+    title: String,
+    link: String,
+    guid: String,
+    enclosure: String,
+    magnet: String,
+}
+
+impl ItemBuilder {
+    // Atom links and rss enclosures carry the url in attributes
+    fn read_attributes(&mut self, element: &BytesStart<'_>) {
+        let mut url = String::new();
+        let mut rel = String::new();
+        let mut mime_type = String::new();
+        for attribute in element.attributes().flatten() {
+            let value = attribute
+                .unescape_value()
+                .map(|v| v.into_owned())
+                .unwrap_or_default();
+            match attribute.key.local_name().as_ref() {
+                b"href" | b"url" => url = value,
+                b"rel" => rel = value,
+                b"type" => mime_type = value,
+                _ => {}
+            }
+        }
+        if url.is_empty() {
+            return;
+        }
+
+        match element.local_name().as_ref() {
+            b"enclosure"
+                if self.enclosure.is_empty() || mime_type == "application/x-bittorrent" =>
+            {
+                self.enclosure = url
+            }
+            b"link" if rel == "enclosure" || mime_type == "application/x-bittorrent" => {
+                self.enclosure = url
+            }
+            b"link" if self.link.is_empty() && (rel.is_empty() || rel == "alternate") => {
+                self.link = url
+            }
+            _ => {}
+        }
+    }
+
+    fn build(self) -> Option<FeedItem> {
+        let link = [self.magnet, self.enclosure, self.link]
+            .into_iter()
+            .map(|l| l.trim().to_string())
+            .find(|l| !l.is_empty())?;
+        let key = Some(self.guid.trim().to_string())
+            .filter(|g| !g.is_empty())
+            .unwrap_or_else(|| link.clone());
+        Some(FeedItem {
+            title: self.title.trim().to_string(),
+            link,
+            key,
+        })
+    }
+}
+
+// Items of an RSS or Atom feed, in the order of the feed
+pub fn parse_feed(text: &str) -> Result<Vec<FeedItem>, String> {
+    let mut reader = Reader::from_str(text);
+    reader.config_mut().trim_text(true);
+
+    let mut items = vec![];
+    let mut item: Option<ItemBuilder> = None;
+    let mut field: Option<Vec<u8>> = None;
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Unable to parse feed: {e}"))?;
+        match event {
+            Event::Start(element) => {
+                let name = element.local_name().as_ref().to_vec();
+                if name == b"item" || name == b"entry" {
+                    item = Some(ItemBuilder::default());
+                } else if let Some(item) = item.as_mut() {
+                    item.read_attributes(&element);
+                    field = Some(name);
+                }
+            }
+            Event::Empty(element) => {
+                if let Some(item) = item.as_mut() {
+                    item.read_attributes(&element);
+                }
+            }
+            Event::Text(_) | Event::CData(_) => {
+                let (Some(item), Some(field)) = (item.as_mut(), field.as_deref()) else {
+                    continue;
+                };
+                let value = match event {
+                    Event::Text(text) => text
+                        .unescape()
+                        .map(|t| t.into_owned())
+                        .map_err(|e| format!("Unable to parse feed: {e}"))?,
+                    Event::CData(data) => String::from_utf8_lossy(&data.into_inner()).into_owned(),
+                    _ => unreachable!(),
+                };
+                match field {
+                    b"title" => item.title.push_str(&value),
+                    b"link" => item.link.push_str(&value),
+                    b"guid" | b"id" => item.guid.push_str(&value),
+                    b"magnetURI" => item.magnet.push_str(&value),
+                    _ => {}
+                }
+            }
+            Event::End(element) => {
+                let name = element.local_name();
+                if name.as_ref() == b"item" || name.as_ref() == b"entry" {
+                    if let Some(item) = item.take().and_then(|i| i.build()) {
+                        items.push(item);
+                    }
+                }
+                field = None;
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(items)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedTestItem {
+    title: String,
+    link: String,
+    // First matching rule
+    rule: Option<String>,
+    episode: Option<String>,
+    in_history: bool,
+}
+
+// Fetches the feed and shows which items the given rules would download
+pub async fn test_feed(
+    app: &AppHandle,
+    feed: &RssFeed,
+    rules: &[RssRule],
+) -> Result<Vec<FeedTestItem>, String> {
+    let config = AppConfig::load(app)?;
+    let handle = app.state::<RssHandle>();
+    test_feed_with(&app.state::<HttpClients>(), &config, feed, rules, |key| {
+        handle.with_history(app, |h| h.contains(key))
+    })
+    .await
+}
+
+async fn test_feed_with(
+    clients: &HttpClients,
+    config: &AppConfig,
+    feed: &RssFeed,
+    rules: &[RssRule],
+    in_history: impl Fn(&str) -> bool,
+) -> Result<Vec<FeedTestItem>, String> {
+    let compiled: Vec<CompiledRule<'_>> = rules
+        .iter()
+        .filter(|r| r.enabled)
+        .map(CompiledRule::new)
+        .collect::<Result<_, _>>()?;
+    let items = fetch_feed(clients, config, &feed.url).await?;
+
+    Ok(items
+        .into_iter()
+        .map(|item| FeedTestItem {
+            rule: compiled
+                .iter()
+                .find(|r| r.matches(&feed.name, &item.title))
+                .map(|r| r.rule.name.clone()),
+            episode: find_episode(&item.title).map(|e| e.to_string()),
+            in_history: in_history(&item.key),
+            title: item.title,
+            link: item.link,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    use super::*;
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:torrent="http://xmlns.ezrss.it/0.1/">
+  <channel>
+    <title>Channel title</title>
+    <item>
+      <title>Show S01E02 720p</title>
+      <link>https://example.com/details/2</link>
+      <guid isPermaLink="false">item-2</guid>
+      <enclosure url="https://example.com/2.torrent" length="100" type="application/x-bittorrent"/>
+    </item>
+    <item>
+      <title><![CDATA[Show S01E01 & more]]></title>
+      <torrent:magnetURI><![CDATA[magnet:?xt=urn:btih:abc]]></torrent:magnetURI>
+    </item>
+    <item>
+      <title>No link</title>
+    </item>
+  </channel>
+</rss>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Feed title</title>
+  <id>urn:uuid:feed</id>
+  <entry>
+    <title type="text">Other Show 2x03 &amp; more</title>
+    <id>urn:uuid:1</id>
+    <link href="https://example.com/page/1"/>
+    <link rel="enclosure" type="application/x-bittorrent" href="https://example.com/1.torrent"/>
+  </entry>
+</feed>"#;
+
+    fn rule(name: &str) -> RssRule {
+        RssRule {
+            enabled: true,
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parses_rss() {
+        let items = parse_feed(RSS).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].title, "Show S01E02 720p");
+        assert_eq!(items[0].link, "https://example.com/2.torrent");
+        assert_eq!(items[0].key, "item-2");
+        assert_eq!(items[1].title, "Show S01E01 & more");
+        assert_eq!(items[1].link, "magnet:?xt=urn:btih:abc");
+        assert_eq!(items[1].key, "magnet:?xt=urn:btih:abc");
+    }
+
+    #[test]
+    fn parses_atom() {
+        let items = parse_feed(ATOM).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title, "Other Show 2x03 & more");
+        assert_eq!(items[0].link, "https://example.com/1.torrent");
+        assert_eq!(items[0].key, "urn:uuid:1");
+    }
+
+    #[test]
+    fn rejects_broken_feed() {
+        assert!(parse_feed("<rss><channel><item><title>a</item></rss>").is_err());
+    }
+
+    #[test]
+    fn parses_episode_filter() {
+        let ranges = parse_episode_filter("1x2; 1x5-8;2X1-").unwrap();
+        let contains = |season, episode| {
+            ranges
+                .iter()
+                .any(|r| r.contains(Episode { season, episode }))
+        };
+        assert!(contains(1, 2));
+        assert!(!contains(1, 3));
+        assert!(contains(1, 5));
+        assert!(contains(1, 8));
+        assert!(!contains(1, 9));
+        assert!(contains(2, 1));
+        assert!(contains(2, 100));
+        assert!(!contains(3, 1));
+
+        assert!(parse_episode_filter("").unwrap().is_empty());
+        for invalid in ["x1", "1x", "1-2", "ax1", "1x2-b"] {
+            assert!(parse_episode_filter(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn finds_episode() {
+        let episode = |title| find_episode(title).map(|e| e.to_string());
+        assert_eq!(episode("Show.S01E02.720p").as_deref(), Some("S01E02"));
+        assert_eq!(episode("Show s2 e103").as_deref(), Some("S02E103"));
+        assert_eq!(episode("Show 3x04 HDTV").as_deref(), Some("S03E04"));
+        assert_eq!(episode("Movie 1080p x264"), None);
+        assert_eq!(episode("Show S01"), None);
+    }
+
+    #[test]
+    fn matches_rules() {
+        let show = RssRule {
+            feeds: vec!["A".to_string()],
+            must_contain: "the show".to_string(),
+            must_not_contain: "720p".to_string(),
+            episodes: "1x1-2".to_string(),
+            ..rule("Show")
+        };
+        let compiled = CompiledRule::new(&show).unwrap();
+        assert!(compiled.matches("A", "The Show S01E01 1080p"));
+        assert!(compiled.matches("A", "THE SHOW S01E02 1080p"));
+        assert!(!compiled.matches("B", "The Show S01E02 1080p"));
+        assert!(!compiled.matches("A", "The Show S01E02 720p"));
+        assert!(!compiled.matches("A", "The Show S01E03 1080p"));
+        assert!(!compiled.matches("A", "The Show 1080p"));
+        assert!(!compiled.matches("A", "Other Show S01E01"));
+
+        let any = rule("Any");
+        assert!(CompiledRule::new(&any).unwrap().matches("B", "Anything"));
+
+        let invalid = RssRule {
+            must_contain: "(".to_string(),
+            ..rule("Invalid")
+        };
+        assert!(CompiledRule::new(&invalid).is_err());
+    }
+
+    #[test]
+    fn selects_rule_without_added_episode() {
+        let rules = [
+            RssRule {
+                must_contain: "show".to_string(),
+                episodes: "1x1-".to_string(),
+                ..rule("HD")
+            },
+            RssRule {
+                must_contain: "show".to_string(),
+                episodes: "1x1-".to_string(),
+                ..rule("SD")
+            },
+            rule("Any"),
+        ];
+        let compiled = compile_rules(&rules);
+        let select = |title: &str, added: &[(&str, &str)]| {
+            select_rule(&compiled, "A", title, |rule, episode| {
+                added.contains(&(rule, episode))
+            })
+            .map(|(rule, episode)| (rule.rule.name.as_str(), episode))
+        };
+
+        assert_eq!(
+            select("Show S01E01", &[]),
+            Some(("HD", Some("S01E01".to_string())))
+        );
+        assert_eq!(
+            select("Show S01E01", &[("HD", "S01E01")]),
+            Some(("SD", Some("S01E01".to_string())))
+        );
+        assert_eq!(
+            select("Show S01E01", &[("HD", "S01E01"), ("SD", "S01E01")]),
+            Some(("Any", None))
+        );
+        assert_eq!(select("Other", &[("HD", "S01E01")]), Some(("Any", None)));
+    }
+
+    #[test]
+    fn tests_feed_from_local_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/feed.xml", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![];
+            let mut buf = [0u8; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let read = stream.read(&mut buf).unwrap();
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..read]);
+            }
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/rss+xml\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{RSS}",
+                RSS.len()
+            )
+            .unwrap();
+        });
+
+        let feed = RssFeed {
+            name: "Local".to_string(),
+            url,
+            ..Default::default()
+        };
+        let rules = vec![
+            RssRule {
+                enabled: false,
+                ..rule("Disabled")
+            },
+            RssRule {
+                episodes: "1x2".to_string(),
+                ..rule("Show")
+            },
+        ];
+        let items = async_runtime::block_on(test_feed_with(
+            &HttpClients::default(),
+            &AppConfig::default(),
+            &feed,
+            &rules,
+            |key| key == "item-2",
+        ))
+        .unwrap();
+        server.join().unwrap();
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].rule.as_deref(), Some("Show"));
+        assert_eq!(items[0].episode.as_deref(), Some("S01E02"));
+        assert!(items[0].in_history);
+        assert_eq!(items[1].rule, None);
+        assert_eq!(items[1].episode.as_deref(), Some("S01E01"));
+        assert!(!items[1].in_history);
+    }
+}
@@ -0,0 +1,74 @@
+// TrguiNG - next gen remote GUI for transmission torrent daemon
+// Copyright (C) 2023  qu1ck (mail at qu1ck.org)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use reqwest::StatusCode;
+
+pub const TRANSMISSION_SESSION_ID: &str = "X-Transmission-Session-Id";
+
+// Transmission session ids per server url, shared by the poller and the proxy
+// so that a rotated session is only negotiated once.
+#[derive(Default)]
+pub struct TransmissionSessions {
+    sessions: Mutex<HashMap<String, String>>,
+}
+
+impl TransmissionSessions {
+    fn get(&self, url: &str) -> Option<String> {
+        self.sessions.lock().unwrap().get(url).cloned()
+    }
+
+    fn set(&self, url: &str, session: String) {
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), session);
+    }
+
+    // Sends the request with the current session id of the server. If the server
+    // responds with 409 then the new session id is stored and the request is
+    // retried once, so callers never see 409 responses unless the retry fails too.
+    pub async fn send(
+        &self,
+        url: &str,
+        request: reqwest::RequestBuilder,
+    ) -> reqwest::Result<reqwest::Response> {
+        let retry = request.try_clone();
+        let request = match self.get(url) {
+            Some(session) => request.header(TRANSMISSION_SESSION_ID, session),
+            None => request,
+        };
+
+        let response = request.send().await?;
+        if response.status() != StatusCode::CONFLICT {
+            return Ok(response);
+        }
+
+        let session = response
+            .headers()
+            .get(TRANSMISSION_SESSION_ID)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        match (session, retry) {
+            (Some(session), Some(retry)) => {
+                self.set(url, session.clone());
+                retry.header(TRANSMISSION_SESSION_ID, session).send().await
+            }
+            _ => Ok(response),
+        }
+    }
+}
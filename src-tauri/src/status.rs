@@ -0,0 +1,239 @@
+// TrguiNG - next gen remote GUI for transmission torrent daemon
+// Copyright (C) 2023  qu1ck (mail at qu1ck.org)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tauri::{image::Image, AppHandle, Manager};
+
+use crate::util::human_size;
+
+// Servers that are no longer polled or whose tab was closed drop out of the status
+const STALE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+// Ordered by priority, the tray shows the highest state of all servers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TrayState {
+    Idle,
+    Downloading,
+    Error,
+    Disconnected,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TransferStats {
+    pub download_speed: i64,
+    pub upload_speed: i64,
+    pub active: usize,
+    pub downloading: usize,
+    pub errors: usize,
+}
+
+struct ServerStatus {
+    name: String,
+    // None when the last update failed
+    stats: Option<TransferStats>,
+    updated: Instant,
+}
+
+// Aggregated transfer status of the servers that the poller or the frontend
+// fetch torrents from, keyed by server url.
+#[derive(Default)]
+pub struct TransferStatusHandle {
+    servers: Mutex<HashMap<String, ServerStatus>>,
+    // Server names by url, updated from the config when it is loaded or changes
+    names: Mutex<HashMap<String, String>>,
+    icon_state: Mutex<Option<TrayState>>,
+}
+
+impl TransferStatusHandle {
+    // Known servers keep their urls and get the new names
+    pub fn set_names(&self, names: impl IntoIterator<Item = (String, String)>) {
+        let names = {
+            let mut known = self.names.lock().unwrap();
+            known.extend(names);
+            known.clone()
+        };
+        for (url, server) in self.servers.lock().unwrap().iter_mut() {
+            if let Some(name) = names.get(url) {
+                server.name = name.clone();
+            }
+        }
+    }
+
+    fn set(&self, url: &str, stats: Option<TransferStats>) {
+        let mut servers = self.servers.lock().unwrap();
+        let now = Instant::now();
+        servers.retain(|_, s| now.duration_since(s.updated) < STALE_TIMEOUT);
+        match servers.get_mut(url) {
+            Some(server) => {
+                server.stats = stats;
+                server.updated = now;
+            }
+            None => {
+                let name = self.names.lock().unwrap().get(url).cloned();
+                servers.insert(
+                    url.to_string(),
+                    ServerStatus {
+                        name: name.unwrap_or_else(|| url.to_string()),
+                        stats,
+                        updated: now,
+                    },
+                );
+            }
+        }
+    }
+
+    pub fn state(&self) -> TrayState {
+        self.servers
+            .lock()
+            .unwrap()
+            .values()
+            .map(|s| match s.stats {
+                None => TrayState::Disconnected,
+                Some(stats) if stats.errors > 0 => TrayState::Error,
+                Some(stats) if stats.downloading > 0 => TrayState::Downloading,
+                Some(_) => TrayState::Idle,
+            })
+            .max()
+            .unwrap_or(TrayState::Idle)
+    }
+
+    // One line per server, None when there is nothing to show
+    pub fn tooltip(&self) -> Option<String> {
+        let servers = self.servers.lock().unwrap();
+        let mut servers: Vec<&ServerStatus> = servers.values().collect();
+        if servers.is_empty() {
+            return None;
+        }
+        servers.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let lines: Vec<String> = servers
+            .iter()
+            .map(|s| format!("{}: {}", s.name, summary(s.stats)))
+            .collect();
+        Some(lines.join("\n"))
+    }
+
+    pub fn server_summary(&self, name: &str) -> String {
+        match self
+            .servers
+            .lock()
+            .unwrap()
+            .values()
+            .find(|s| s.name == name)
+        {
+            Some(server) => summary(server.stats),
+            None => "Not polled yet".to_string(),
+        }
+    }
+
+    // Returns the new state when the icon needs to change
+    pub fn icon_change(&self) -> Option<TrayState> {
+        let state = self.state();
+        let mut icon_state = self.icon_state.lock().unwrap();
+        if *icon_state == Some(state) {
+            return None;
+        }
+        *icon_state = Some(state);
+        Some(state)
+    }
+
+    // Called when the tray is recreated with the default icon
+    pub fn reset_icon(&self) {
+        *self.icon_state.lock().unwrap() = Some(TrayState::Idle);
+    }
+}
+
+fn summary(stats: Option<TransferStats>) -> String {
+    match stats {
+        None => "disconnected".to_string(),
+        Some(stats) => {
+            let mut summary = format!(
+                "↓ {}/s ↑ {}/s, {} active",
+                human_size(stats.download_speed),
+                human_size(stats.upload_speed),
+                stats.active
+            );
+            if stats.errors > 0 {
+                summary.push_str(&format!(", {} errors", stats.errors));
+            }
+            summary
+        }
+    }
+}
+
+pub fn update(app: &AppHandle, url: &str, stats: TransferStats) {
+    app.state::<TransferStatusHandle>().set(url, Some(stats));
+    crate::tray::update_status(app);
+}
+
+pub fn set_disconnected(app: &AppHandle, url: &str) {
+    app.state::<TransferStatusHandle>().set(url, None);
+    crate::tray::update_status(app);
+}
+
+// App icon with a status badge in the bottom right corner, disconnected state
+// is also grayed out.
+pub fn status_icon(base: &Image<'_>, state: TrayState) -> Image<'static> {
+    let (width, height) = (base.width(), base.height());
+    let mut rgba = base.rgba().to_vec();
+
+    let color: [u8; 3] = match state {
+        TrayState::Idle => return Image::new_owned(rgba, width, height),
+        TrayState::Downloading => [0x2f, 0x9e, 0x44],
+        TrayState::Error => [0xe0, 0x31, 0x31],
+        TrayState::Disconnected => [0x86, 0x8e, 0x96],
+    };
+
+    if state == TrayState::Disconnected {
+        for pixel in rgba.chunks_exact_mut(4) {
+            let gray =
+                (0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32) as u8;
+            pixel[0] = gray;
+            pixel[1] = gray;
+            pixel[2] = gray;
+        }
+    }
+
+    let size = width.min(height) as f32;
+    let radius = size * 0.2;
+    let border = (size / 32.0).max(1.0);
+    let center_x = width as f32 - radius - border;
+    let center_y = height as f32 - radius - border;
+    for y in 0..height {
+        for x in 0..width {
+            let dx = x as f32 + 0.5 - center_x;
+            let dy = y as f32 + 0.5 - center_y;
+            let distance = (dx * dx + dy * dy).sqrt();
+            let fill = if distance <= radius {
+                color
+            } else if distance <= radius + border {
+                [0xff, 0xff, 0xff]
+            } else {
+                continue;
+            };
+            let i = ((y * width + x) * 4) as usize;
+            rgba[i..i + 3].copy_from_slice(&fill);
+            rgba[i + 3] = 0xff;
+        }
+    }
+
+    Image::new_owned(rgba, width, height)
+}
@@ -14,22 +14,40 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io::{self, BufReader, Read},
+    sync::Arc,
+};
 
+use hyper::body::Bytes;
 use serde::Deserialize;
 use tauri::{
     async_runtime::{self, Mutex},
     AppHandle, Manager, State,
 };
 use tauri_plugin_notification::NotificationExt;
+use tokio::sync::mpsc::{self, Receiver, Sender};
 
 use crate::sound::play_ping;
+use crate::status::{self, TransferStats};
 
 #[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
 struct Torrent {
     id: i64,
     name: String,
     status: i64,
+    #[serde(default)]
+    rate_download: i64,
+    #[serde(default)]
+    rate_upload: i64,
+    #[serde(default)]
+    error: i64,
+    #[serde(default)]
+    download_dir: String,
+    #[serde(default)]
+    hash_string: String,
 }
 
 #[derive(Deserialize, Debug, Default)]
@@ -53,6 +71,95 @@ pub struct TorrentCache {
 #[derive(Default)]
 pub struct TorrentCacheHandle(Arc<Mutex<TorrentCache>>);
 
+impl TorrentCacheHandle {
+    // Lowercase info hashes of the torrents last seen on the server
+    pub async fn hashes(&self, server_url: &str) -> HashSet<String> {
+        self.0
+            .lock()
+            .await
+            .server_data
+            .get(server_url)
+            .map(|torrents| {
+                torrents
+                    .values()
+                    .map(|t| t.hash_string.to_lowercase())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+const RECENT_COMPLETED_SIZE: usize = 10;
+
+#[derive(Clone)]
+pub struct CompletedTorrent {
+    // Unique for the lifetime of the app, identifies menu items
+    pub seq: u64,
+    pub name: String,
+    // Path of the torrent data on the server
+    pub path: String,
+    pub server_url: String,
+}
+
+impl CompletedTorrent {
+    fn new(torrent: &Torrent, server_url: &str) -> Self {
+        let mut path = torrent.download_dir.clone();
+        if !path.is_empty() && !path.ends_with('/') && !path.ends_with('\\') {
+            path.push('/');
+        }
+        path.push_str(&file_system_safe_name(&torrent.name));
+        Self {
+            seq: 0,
+            name: torrent.name.clone(),
+            path,
+            server_url: server_url.to_string(),
+        }
+    }
+}
+
+// Same replacement as the frontend does when it opens torrent paths
+fn file_system_safe_name(name: &str) -> String {
+    if name.is_empty() {
+        return "_".to_string();
+    }
+    name.replace(['<', '>', ':', '"', '\\', '/', '|', '?', '*'], "_")
+}
+
+// Last completed torrents of all servers, newest first
+#[derive(Default)]
+pub struct RecentCompletedHandle(std::sync::Mutex<RecentCompleted>);
+
+#[derive(Default)]
+struct RecentCompleted {
+    torrents: VecDeque<CompletedTorrent>,
+    next_seq: u64,
+}
+
+impl RecentCompletedHandle {
+    fn push(&self, completed: Vec<CompletedTorrent>) {
+        let mut recent = self.0.lock().unwrap();
+        for mut torrent in completed {
+            torrent.seq = recent.next_seq;
+            recent.next_seq += 1;
+            recent.torrents.push_front(torrent);
+        }
+        recent.torrents.truncate(RECENT_COMPLETED_SIZE);
+    }
+
+    pub fn list(&self) -> Vec<CompletedTorrent> {
+        self.0.lock().unwrap().torrents.iter().cloned().collect()
+    }
+
+    pub fn get(&self, seq: u64) -> Option<CompletedTorrent> {
+        let recent = self.0.lock().unwrap();
+        recent.torrents.iter().find(|t| t.seq == seq).cloned()
+    }
+
+    pub fn clear(&self) {
+        self.0.lock().unwrap().torrents.clear();
+    }
+}
+
 pub async fn process_response(
     app: &AppHandle,
     response: &[u8],
@@ -60,7 +167,89 @@ pub async fn process_response(
     toast: bool,
     sound: bool,
 ) -> Result<(), String> {
-    match serde_json::from_slice::<ServerResponse>(response) {
+    process_parsed_response(
+        app,
+        serde_json::from_slice::<ServerResponse>(response),
+        original_url,
+        toast,
+        sound,
+    )
+    .await;
+
+    Ok(())
+}
+
+// Blocking reader over response chunks as they arrive from the proxied stream.
+struct ChunkReader {
+    rx: Receiver<Bytes>,
+    chunk: Bytes,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.chunk.is_empty() {
+            match self.rx.blocking_recv() {
+                Some(chunk) => self.chunk = chunk,
+                None => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.chunk.len());
+        buf[..len].copy_from_slice(&self.chunk.split_to(len));
+        Ok(len)
+    }
+}
+
+const PARSER_BUFFER_SIZE: usize = 64 * 1024;
+// Chunks waiting for the parser, when it falls this far behind the sender waits
+// for it briefly and then gives up instead of buffering the whole response.
+const PARSER_CHANNEL_SIZE: usize = 256;
+
+fn parse_chunks(rx: Receiver<Bytes>) -> serde_json::Result<ServerResponse> {
+    let reader = BufReader::with_capacity(
+        PARSER_BUFFER_SIZE,
+        ChunkReader {
+            rx,
+            chunk: Bytes::new(),
+        },
+    );
+    serde_json::from_reader::<_, ServerResponse>(reader)
+}
+
+// Spawns a parser that consumes response chunks sent to the returned channel.
+// Only the fields of the torrents that the cache needs are kept in memory, the
+// rest of the response is discarded as it is parsed. Dropping the sender marks
+// the end of the response, a truncated response fails to parse and is ignored.
+pub fn spawn_response_parser(
+    app: &AppHandle,
+    original_url: String,
+    toast: bool,
+    sound: bool,
+) -> Sender<Bytes> {
+    let (tx, rx) = mpsc::channel::<Bytes>(PARSER_CHANNEL_SIZE);
+    let app = app.clone();
+
+    async_runtime::spawn(async move {
+        let parse_result = async_runtime::spawn_blocking(move || parse_chunks(rx)).await;
+
+        match parse_result {
+            Ok(parsed) => {
+                process_parsed_response(&app, parsed, original_url.as_str(), toast, sound).await
+            }
+            Err(e) => println!("Response parser failed {e:?}"),
+        }
+    });
+
+    tx
+}
+
+async fn process_parsed_response(
+    app: &AppHandle,
+    parsed: serde_json::Result<ServerResponse>,
+    original_url: &str,
+    toast: bool,
+    sound: bool,
+) {
+    match parsed {
         Ok(server_response) => {
             if server_response.result != "success" {
                 println!("Server returned error {}", server_response.result);
@@ -74,8 +263,6 @@ pub async fn process_response(
         }
         Err(e) => println!("Failed to parse server response {e:?}"),
     };
-
-    Ok(())
 }
 
 async fn process_torrents(
@@ -98,6 +285,7 @@ async fn process_torrents(
     let cache_handle: State<TorrentCacheHandle> = app.state();
     let mut cache = cache_handle.0.lock().await;
 
+    let mut completed = vec![];
     if let Some(old_map) = cache.server_data.get::<str>(original_url) {
         let mut play_sound = false;
         old_map.iter().for_each(|(id, old_torrent)| {
@@ -114,6 +302,7 @@ async fn process_torrents(
                     if toast {
                         show_notification(app, new_torrent.name.as_str());
                     }
+                    completed.push(CompletedTorrent::new(new_torrent, original_url));
                 }
             } else if partial_update {
                 map.insert(
@@ -122,6 +311,11 @@ async fn process_torrents(
                         id: *id,
                         name: old_torrent.name.clone(),
                         status: old_torrent.status,
+                        rate_download: old_torrent.rate_download,
+                        rate_upload: old_torrent.rate_upload,
+                        error: old_torrent.error,
+                        download_dir: old_torrent.download_dir.clone(),
+                        hash_string: old_torrent.hash_string.clone(),
                     },
                 );
             }
@@ -131,7 +325,33 @@ async fn process_torrents(
         }
     }
 
+    let stats = transfer_stats(&map);
     cache.server_data.insert(original_url.into(), map);
+    drop(cache);
+
+    status::update(app, original_url, stats);
+    if !completed.is_empty() {
+        app.state::<RecentCompletedHandle>().push(completed);
+        crate::tray::rebuild_menu(app);
+    }
+}
+
+fn transfer_stats(torrents: &HashMap<i64, Torrent>) -> TransferStats {
+    let mut stats = TransferStats::default();
+    for t in torrents.values() {
+        stats.download_speed += t.rate_download;
+        stats.upload_speed += t.rate_upload;
+        if t.rate_download > 0 || t.rate_upload > 0 {
+            stats.active += 1;
+        }
+        if t.status == 4 {
+            stats.downloading += 1;
+        }
+        if t.error != 0 {
+            stats.errors += 1;
+        }
+    }
+    stats
 }
 
 fn show_notification(app: &AppHandle, name: &str) {
@@ -145,3 +365,65 @@ fn show_notification(app: &AppHandle, name: &str) {
         println!("Cannot show notification: {e:?}");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESPONSE: &str = r#"{
+        "arguments": {
+            "torrents": [
+                {"id": 1, "name": "first", "status": 4, "rateDownload": 1000, "peers": [{"address": "127.0.0.1"}]},
+                {"id": 2, "name": "second", "status": 6, "rateUpload": 500, "error": 3, "hashString": "abc"}
+            ]
+        },
+        "result": "success"
+    }"#;
+
+    // Feeds the text to the parser in chunks of the given size
+    fn parse_split(text: &str, chunk_size: usize) -> serde_json::Result<ServerResponse> {
+        let (tx, rx) = mpsc::channel::<Bytes>(PARSER_CHANNEL_SIZE);
+        for chunk in text.as_bytes().chunks(chunk_size) {
+            tx.try_send(Bytes::copy_from_slice(chunk)).unwrap();
+        }
+        drop(tx);
+        parse_chunks(rx)
+    }
+
+    #[test]
+    fn parses_split_response() {
+        for chunk_size in [3, 7, RESPONSE.len()] {
+            let response = parse_split(RESPONSE, chunk_size).unwrap();
+            assert_eq!(response.result, "success");
+            let torrents = response.arguments.unwrap().torrents;
+            assert_eq!(torrents.len(), 2);
+            assert_eq!(torrents[0].name, "first");
+            assert_eq!(torrents[0].rate_download, 1000);
+            assert_eq!(torrents[1].hash_string, "abc");
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_response() {
+        assert!(parse_split(&RESPONSE[..RESPONSE.len() / 2], 16).is_err());
+        assert!(parse_split("", 16).is_err());
+    }
+
+    #[test]
+    fn sums_transfer_stats() {
+        let torrents: HashMap<i64, Torrent> = parse_split(RESPONSE, 64)
+            .unwrap()
+            .arguments
+            .unwrap()
+            .torrents
+            .into_iter()
+            .map(|t| (t.id, t))
+            .collect();
+        let stats = transfer_stats(&torrents);
+        assert_eq!(stats.download_speed, 1000);
+        assert_eq!(stats.upload_speed, 500);
+        assert_eq!(stats.active, 2);
+        assert_eq!(stats.downloading, 1);
+        assert_eq!(stats.errors, 1);
+    }
+}
@@ -14,16 +14,24 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
 use tauri::{
     async_runtime,
-    menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem},
+    menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    AppHandle, Emitter, Listener, Manager, State, WebviewWindow, WebviewWindowBuilder,
+    AppHandle, Emitter, Listener, Manager, State, WebviewWindow, WebviewWindowBuilder, Wry,
 };
 use tokio::sync::oneshot;
 
+use crate::actions::{self, speed_label, speed_presets, ServerAction};
+use crate::appconfig::AppConfig;
+use crate::ipc::{show_notification, AddArgs};
+use crate::status::{status_icon, TransferStatusHandle};
+use crate::torrentcache::RecentCompletedHandle;
 use crate::ListenerHandle;
 
 pub const TRAY_ID: &str = "tray";
@@ -62,17 +70,268 @@ pub fn create_tray(app: AppHandle) {
             .join("tray-icon")
             .join(&app.config().identifier));
 
-    builder.build(&app).ok();
+    if builder.build(&app).is_ok() {
+        app.state::<TransferStatusHandle>().reset_icon();
+        update_status(&app);
+    }
 }
 
-fn create_menu<R>(app: &AppHandle<R>, showhide_text: &str) -> tauri::menu::Menu<R>
-where
-    R: tauri::Runtime,
-{
+// Shows transfer status of the servers in the tooltip and the icon
+pub fn update_status(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let status = app.state::<TransferStatusHandle>();
+    tray.set_tooltip(status.tooltip()).ok();
+    if let (Some(state), Some(icon)) = (status.icon_change(), app.default_window_icon()) {
+        tray.set_icon(Some(status_icon(icon, state))).ok();
+    }
+
+    // Menu items are updated on the main thread, don't hold the lock meanwhile
+    let status_items = app
+        .state::<TrayMenuHandle>()
+        .0
+        .lock()
+        .unwrap()
+        .status_items
+        .clone();
+    for (server, item) in status_items {
+        item.set_text(status.server_summary(&server)).ok();
+    }
+}
+
+// Servers of the poller get their own submenus, their status items are
+// updated in place on every poll.
+#[derive(Default)]
+struct TrayMenuState {
+    showhide_text: String,
+    servers: Vec<String>,
+    status_items: HashMap<String, MenuItem<Wry>>,
+}
+
+#[derive(Default)]
+pub struct TrayMenuHandle(Mutex<TrayMenuState>);
+
+fn create_menu(app: &AppHandle, showhide_text: &str) -> Menu<Wry> {
+    let menu_state = app.state::<TrayMenuHandle>();
+    let servers = menu_state.0.lock().unwrap().servers.clone();
+
     let hide = MenuItem::with_id(app, "showhide", showhide_text, true, None::<&str>).unwrap();
     let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>).unwrap();
     let separator = PredefinedMenuItem::separator(app).unwrap();
-    Menu::with_items(app, &[&hide, &separator, &quit]).unwrap()
+    let separator2 = PredefinedMenuItem::separator(app).unwrap();
+    let separator3 = PredefinedMenuItem::separator(app).unwrap();
+    let action_item = |action: ServerAction, text: &str| {
+        MenuItem::with_id(app, action.id(), text, true, None::<&str>).unwrap()
+    };
+    let pause = action_item(ServerAction::PauseAll, "Pause all");
+    let resume = action_item(ServerAction::ResumeAll, "Resume all");
+    let altspeed = action_item(
+        ServerAction::ToggleAltSpeed,
+        "Toggle alternative speed limits",
+    );
+    let limits = create_limits_menu(app);
+    let recent = create_recent_menu(app);
+    let clipboard = action_item(ServerAction::AddFromClipboard, "Add torrent from clipboard");
+    let copied = crate::clipboard::pending(app);
+
+    let menu = Menu::with_items(app, &[&hide, &separator]).unwrap();
+    let mut status_items = HashMap::new();
+    for server in servers.iter() {
+        let (submenu, status_item) = create_server_menu(app, server);
+        menu.append(&submenu).unwrap();
+        status_items.insert(server.clone(), status_item);
+    }
+    if !servers.is_empty() {
+        menu.append(&PredefinedMenuItem::separator(app).unwrap())
+            .unwrap();
+    }
+    menu.append_items(&[
+        &recent,
+        &separator3,
+        &pause,
+        &resume,
+        &altspeed,
+        &limits,
+        &clipboard,
+    ])
+    .unwrap();
+    if !copied.is_empty() {
+        let text = crate::clipboard::pending_item_text(&copied);
+        let add_copied = MenuItem::with_id(app, "clipboard:add", text, true, None::<&str>).unwrap();
+        menu.append(&add_copied).unwrap();
+    }
+    menu.append_items(&[&separator2, &quit]).unwrap();
+
+    let mut state = menu_state.0.lock().unwrap();
+    state.showhide_text = showhide_text.to_string();
+    state.status_items = status_items;
+
+    menu
+}
+
+fn server_item_id(server: &str, item: &str) -> String {
+    format!("server:{server}:{item}")
+}
+
+// Server names may contain ':', item ids never do
+fn parse_server_item_id(id: &str) -> Option<(&str, &str)> {
+    id.strip_prefix("server:")?.rsplit_once(':')
+}
+
+fn create_server_menu(app: &AppHandle, server: &str) -> (Submenu<Wry>, MenuItem<Wry>) {
+    let status = app.state::<TransferStatusHandle>().server_summary(server);
+    let status_item = MenuItem::with_id(
+        app,
+        server_item_id(server, "status"),
+        status,
+        false,
+        None::<&str>,
+    )
+    .unwrap();
+    let separator = PredefinedMenuItem::separator(app).unwrap();
+    let item = |id: String, text: &str| {
+        MenuItem::with_id(app, server_item_id(server, &id), text, true, None::<&str>).unwrap()
+    };
+    let open = item("open".to_string(), "Open");
+    let pause = item(ServerAction::PauseAll.id(), "Pause all");
+    let resume = item(ServerAction::ResumeAll.id(), "Resume all");
+    let submenu = Submenu::with_items(
+        app,
+        server,
+        true,
+        &[&status_item, &separator, &open, &pause, &resume],
+    )
+    .unwrap();
+    (submenu, status_item)
+}
+
+// Rebuilds the menu when the polled servers change
+pub fn set_servers(app: &AppHandle, mut servers: Vec<String>) {
+    servers.sort();
+    {
+        let menu_state = app.state::<TrayMenuHandle>();
+        let mut state = menu_state.0.lock().unwrap();
+        if state.servers == servers {
+            return;
+        }
+        state.servers = servers;
+    }
+    rebuild_menu(app);
+}
+
+pub fn rebuild_menu(app: &AppHandle) {
+    let showhide_text = app
+        .state::<TrayMenuHandle>()
+        .0
+        .lock()
+        .unwrap()
+        .showhide_text
+        .clone();
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        tray.set_menu(Some(create_menu(app, &showhide_text))).ok();
+    }
+}
+
+fn create_recent_menu(app: &AppHandle) -> Submenu<Wry> {
+    let submenu = Submenu::new(app, "Recently completed", true).unwrap();
+    let recent = app.state::<RecentCompletedHandle>().list();
+    if recent.is_empty() {
+        let empty = MenuItem::new(app, "No completed torrents", false, None::<&str>).unwrap();
+        submenu.append(&empty).unwrap();
+        return submenu;
+    }
+
+    for torrent in recent.iter() {
+        let item = MenuItem::with_id(
+            app,
+            format!("recent:{}", torrent.seq),
+            &torrent.name,
+            true,
+            None::<&str>,
+        )
+        .unwrap();
+        submenu.append(&item).unwrap();
+    }
+    let separator = PredefinedMenuItem::separator(app).unwrap();
+    let clear = MenuItem::with_id(app, "recent:clear", "Clear", true, None::<&str>).unwrap();
+    submenu.append_items(&[&separator, &clear]).unwrap();
+    submenu
+}
+
+fn on_recent_event(app: &AppHandle, id: &str) {
+    if id == "clear" {
+        app.state::<RecentCompletedHandle>().clear();
+        rebuild_menu(app);
+        return;
+    }
+
+    let Some(torrent) = id
+        .parse::<u64>()
+        .ok()
+        .and_then(|seq| app.state::<RecentCompletedHandle>().get(seq))
+    else {
+        return;
+    };
+    let app = app.clone();
+    async_runtime::spawn(async move {
+        let path = match AppConfig::load(&app) {
+            Ok(config) => config.map_path_from_server(&torrent.server_url, &torrent.path),
+            Err(_) => torrent.path,
+        };
+        if let Err(e) = crate::commands::shell_open(path.clone(), true).await {
+            show_notification(&app, "Error opening path", &format!("{path}: {e}"));
+        }
+    });
+}
+
+// Opens the main window on the tab of the server, same way as passing the server
+// on the command line.
+fn open_server_tab(app: &AppHandle, server: &str) {
+    let app = app.clone();
+    let args = AddArgs {
+        server: Some(server.to_string()),
+        ..Default::default()
+    };
+    async_runtime::spawn(async move {
+        let listener_state: State<ListenerHandle> = app.state();
+        let listener = listener_state.0.read().await;
+        if let Err(e) = listener.send(&args, app.clone()).await {
+            println!("Unable to open server tab: {e}");
+        }
+    });
+}
+
+fn create_limits_menu<R>(app: &AppHandle<R>) -> Submenu<R>
+where
+    R: tauri::Runtime,
+{
+    let download = create_presets_menu(app, "Download", ServerAction::DownloadLimit);
+    let upload = create_presets_menu(app, "Upload", ServerAction::UploadLimit);
+    Submenu::with_items(app, "Speed limits", true, &[&download, &upload]).unwrap()
+}
+
+fn create_presets_menu<R>(
+    app: &AppHandle<R>,
+    text: &str,
+    action: fn(Option<u64>) -> ServerAction,
+) -> Submenu<R>
+where
+    R: tauri::Runtime,
+{
+    let submenu = Submenu::new(app, text, true).unwrap();
+    for limit in speed_presets() {
+        let item = MenuItem::with_id(
+            app,
+            action(limit).id(),
+            speed_label(limit),
+            true,
+            None::<&str>,
+        )
+        .unwrap();
+        submenu.append(&item).unwrap();
+    }
+    submenu
 }
 
 pub fn set_tray_showhide_text(app: &AppHandle, text: &str) {
@@ -89,7 +348,22 @@ fn on_menu_event(app: &AppHandle, event: MenuEvent) {
         "showhide" => {
             toggle_main_window(app, app.get_webview_window("main"));
         }
-        _ => {}
+        "clipboard:add" => {
+            crate::clipboard::add_pending(app);
+        }
+        id => {
+            if let Some(id) = id.strip_prefix("recent:") {
+                on_recent_event(app, id);
+            } else if let Some((server, item)) = parse_server_item_id(id) {
+                if item == "open" {
+                    open_server_tab(app, server);
+                } else if let Some(action) = ServerAction::from_id(item) {
+                    actions::spawn(app, Some(server.to_string()), action);
+                }
+            } else if let Some(action) = ServerAction::from_id(id) {
+                actions::spawn(app, None, action);
+            }
+        }
     }
 }
 
@@ -109,16 +383,38 @@ pub fn toggle_main_window(app: &AppHandle, window: Option<WebviewWindow>) {
                 close_main(window).await;
             });
         }
-        None => {
-            let window =
-                WebviewWindowBuilder::new(app, "main", tauri::WebviewUrl::App("index.html".into()))
-                    .title("Transmission GUI")
-                    .inner_size(1024., 800.)
-                    .build()
-                    .unwrap();
-            set_tray_showhide_text(app, "Hide");
-            window.set_focus().ok();
-        }
+        None => create_main_window(app, false),
+    }
+}
+
+// A minimized window is created hidden and only shown once it is minimized,
+// otherwise it would flash on screen at startup.
+pub fn create_main_window(app: &AppHandle, minimized: bool) {
+    let window =
+        WebviewWindowBuilder::new(app, "main", tauri::WebviewUrl::App("index.html".into()))
+            .title("Transmission GUI")
+            .inner_size(1024., 800.)
+            .visible(!minimized)
+            .focused(!minimized)
+            .build()
+            .unwrap();
+    set_tray_showhide_text(app, "Hide");
+    if minimized {
+        window.minimize().ok();
+        window.show().ok();
+    } else {
+        window.set_focus().ok();
+    }
+}
+
+// Destroys the webview to free its memory, the backend keeps polling, showing
+// notifications and the tray status until the window is opened again.
+pub fn unload_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        set_tray_showhide_text(app, "Show");
+        async_runtime::spawn(async move {
+            close_main(window).await;
+        });
     }
 }
 
@@ -151,3 +447,28 @@ async fn close_main(window: WebviewWindow) {
     rx.await.ok();
     window.destroy().ok();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_server_item_ids() {
+        let items = [
+            "open".to_string(),
+            "status".to_string(),
+            ServerAction::PauseAll.id(),
+            ServerAction::DownloadLimit(Some(500)).id(),
+        ];
+        for server in ["home", "nas:9091", "a:b:c", ""] {
+            for item in &items {
+                assert_eq!(
+                    parse_server_item_id(&server_item_id(server, item)),
+                    Some((server, item.as_str()))
+                );
+            }
+        }
+        assert_eq!(parse_server_item_id("recent:server:x"), None);
+        assert_eq!(parse_server_item_id("pauseall"), None);
+    }
+}
@@ -0,0 +1,262 @@
+// TrguiNG - next gen remote GUI for transmission torrent daemon
+// Copyright (C) 2023  qu1ck (mail at qu1ck.org)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use serde::Deserialize;
+use tauri::{
+    async_runtime::{self, JoinHandle},
+    AppHandle, Manager,
+};
+
+use crate::appconfig::AppConfig;
+use crate::httpclient::HttpClients;
+use crate::ipc::show_notification;
+use crate::rpc::{AddOptions, AddResult, Rpc};
+use crate::session::TransmissionSessions;
+
+const SCAN_INTERVAL: Duration = Duration::from_secs(5);
+// Files modified more recently than this may still be being written
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+// Processed files are moved here, relative to the watched folder, unless set otherwise
+const DEFAULT_MOVE_DIR: &str = "processed";
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AfterAdd {
+    #[default]
+    Delete,
+    Move,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WatchFolder {
+    pub enabled: bool,
+    pub path: String,
+    // Empty for the active server
+    pub server: String,
+    pub download_dir: String,
+    pub labels: Vec<String>,
+    pub paused: bool,
+    pub after_add: AfterAdd,
+    pub move_to: String,
+}
+
+impl WatchFolder {
+    fn options(&self) -> AddOptions {
+        AddOptions {
+            download_dir: Some(self.download_dir.clone()).filter(|d| !d.is_empty()),
+            labels: self.labels.clone(),
+            paused: Some(self.paused),
+            priority: None,
+        }
+    }
+
+    fn move_dir(&self) -> Option<String> {
+        match self.after_add {
+            AfterAdd::Delete => None,
+            AfterAdd::Move if self.move_to.is_empty() => Some(
+                Path::new(&self.path)
+                    .join(DEFAULT_MOVE_DIR)
+                    .to_string_lossy()
+                    .into_owned(),
+            ),
+            AfterAdd::Move => Some(self.move_to.clone()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct WatchState {
+    folders: Vec<WatchFolder>,
+    task: Option<JoinHandle<()>>,
+}
+
+#[derive(Default)]
+pub struct WatchFoldersHandle(Mutex<WatchState>);
+
+// Restarts the watcher when the folders change, it runs without the main window
+pub fn set_folders(app: &AppHandle, folders: Vec<WatchFolder>) {
+    let handle = app.state::<WatchFoldersHandle>();
+    let mut state = handle.0.lock().unwrap();
+    if state.folders == folders && state.task.is_some() {
+        return;
+    }
+
+    if let Some(task) = state.task.take() {
+        task.abort();
+    }
+    let enabled: Vec<WatchFolder> = folders
+        .iter()
+        .filter(|f| f.enabled && !f.path.is_empty())
+        .cloned()
+        .collect();
+    if !enabled.is_empty() {
+        state.task = Some(async_runtime::spawn(watch_task(app.clone(), enabled)));
+    }
+    state.folders = folders;
+}
+
+async fn watch_task(app: AppHandle, folders: Vec<WatchFolder>) {
+    // Files that could not be added, or not removed after adding, are retried
+    // only after they change
+    let mut failed = HashMap::<PathBuf, SystemTime>::new();
+
+    loop {
+        for folder in folders.iter() {
+            scan_folder(&app, folder, &mut failed).await;
+        }
+        tokio::time::sleep(SCAN_INTERVAL).await;
+    }
+}
+
+fn is_watched_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            ext.eq_ignore_ascii_case("torrent") || ext.eq_ignore_ascii_case("magnet")
+        })
+}
+
+// Watched files in the folder with their modification times
+fn list_files(path: &str) -> std::io::Result<Vec<(PathBuf, SystemTime)>> {
+    Ok(std::fs::read_dir(path)?
+        .flatten()
+        .filter(|entry| is_watched_file(&entry.path()))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok().filter(|m| m.is_file())?;
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            Some((entry.path(), modified))
+        })
+        .collect())
+}
+
+// Files are added once they stopped changing, a failed file only after it was modified again
+fn should_add(modified: SystemTime, now: SystemTime, failed: Option<&SystemTime>) -> bool {
+    now.duration_since(modified)
+        .is_ok_and(|age| age >= SETTLE_TIME)
+        && failed != Some(&modified)
+}
+
+async fn scan_folder(
+    app: &AppHandle,
+    folder: &WatchFolder,
+    failed: &mut HashMap<PathBuf, SystemTime>,
+) {
+    let dir = folder.path.clone();
+    let files = match async_runtime::spawn_blocking(move || list_files(&dir)).await {
+        Ok(Ok(files)) => files,
+        Ok(Err(e)) => {
+            println!("Unable to read watch folder {}: {e}", folder.path);
+            return;
+        }
+        Err(e) => {
+            println!("Unable to scan watch folder {}: {e}", folder.path);
+            return;
+        }
+    };
+
+    let now = SystemTime::now();
+    for (path, modified) in files {
+        if !should_add(modified, now, failed.get(&path)) {
+            continue;
+        }
+
+        let path_str = path.to_string_lossy().into_owned();
+        match add_file(app, folder, &path).await {
+            Ok(result) => {
+                failed.remove(&path);
+                let title = if result.duplicate {
+                    "Torrent already exists"
+                } else {
+                    "Torrent added"
+                };
+                show_notification(app, title, &result.name);
+                if let Err(e) = crate::commands::remove_file(path_str, folder.move_dir()).await {
+                    failed.insert(path, modified);
+                    show_notification(app, "Unable to clean up watch folder", &e);
+                }
+            }
+            Err(e) => {
+                failed.insert(path, modified);
+                show_notification(app, "Unable to add torrent", &format!("{path_str}: {e}"));
+            }
+        }
+    }
+}
+
+async fn add_file(app: &AppHandle, folder: &WatchFolder, path: &Path) -> Result<AddResult, String> {
+    // .magnet files contain the link as text
+    let torrent = if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("magnet"))
+    {
+        let text = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| format!("Unable to read file: {e}"))?;
+        text.lines()
+            .map(|l| l.trim())
+            .find(|l| l.starts_with("magnet:?"))
+            .ok_or("File does not contain a magnet link")?
+            .to_string()
+    } else {
+        path.to_string_lossy().into_owned()
+    };
+
+    let config = AppConfig::load(app)?;
+    let server = Some(folder.server.as_str()).filter(|s| !s.is_empty());
+    let connection = config
+        .server_connection(server)
+        .ok_or_else(|| format!("Server {:?} not found", folder.server))?;
+    let clients = app.state::<HttpClients>();
+    let sessions = app.state::<TransmissionSessions>();
+    Rpc::new(&clients, &sessions, &connection)
+        .with_config(&config)
+        .add_torrent(&torrent, &folder.options())
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waits_for_files_to_settle() {
+        let now = SystemTime::now();
+        assert!(should_add(now - SETTLE_TIME, now, None));
+        assert!(should_add(now - Duration::from_secs(60), now, None));
+        assert!(!should_add(now - Duration::from_secs(1), now, None));
+        assert!(!should_add(now, now, None));
+        // Clock skew on network shares
+        assert!(!should_add(now + Duration::from_secs(60), now, None));
+    }
+
+    #[test]
+    fn retries_failed_files_after_change() {
+        let now = SystemTime::now();
+        let failed_at = now - Duration::from_secs(60);
+        assert!(!should_add(failed_at, now, Some(&failed_at)));
+        let modified = now - Duration::from_secs(10);
+        assert!(should_add(modified, now, Some(&failed_at)));
+        assert!(!should_add(now, now, Some(&failed_at)));
+    }
+}
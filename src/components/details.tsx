@@ -445,10 +445,16 @@ const DetailsPanels = React.memo(function DetailsPanels({ torrent }: { torrent:
 function Details(props: DetailsProps) {
     const config = useContext(ConfigContext);
     const peersTableVisibility = config.getTableColumnVisibility("peers");
-    const countryVisible = peersTableVisibility.country ?? true;
+    const lookupIps = (peersTableVisibility.country ?? true) ||
+        (peersTableVisibility.cachedLocation ?? true) ||
+        (peersTableVisibility.cachedOrganization ?? true);
+    const resolveHostnames = peersTableVisibility.cachedHostname ?? false;
+    const lookups = useMemo(
+        () => ({ geoip: lookupIps, hostnames: resolveHostnames }),
+        [lookupIps, resolveHostnames]);
 
     const { data: fetchedTorrent, isLoading } = useTorrentDetails(
-        props.torrentId ?? -1, props.torrentId !== undefined && props.updates, countryVisible);
+        props.torrentId ?? -1, props.torrentId !== undefined && props.updates, lookups);
 
     const [torrent, setTorrent] = useState<Torrent>();
 
@@ -96,15 +96,26 @@ function AddCommon(props: AddCommonProps) {
     </>;
 }
 
+// Options passed on the command line, they override dialog defaults
+export interface AddOptions {
+    downloadDir?: string,
+    labels?: string[],
+    paused?: boolean,
+    priority?: PriorityNumberType,
+}
+
 interface AddCommonModalProps extends ModalState {
     serverName: string,
-    uri: string | File | undefined,
+    // Multiple paths are read in one batch
+    uri: string | string[] | File | undefined,
+    options?: AddOptions,
     tabsRef: React.RefObject<ServerTabsRef>,
 }
 
-function useCommonProps(opened: boolean) {
+function useCommonProps(opened: boolean, options?: AddOptions) {
     const config = useContext(ConfigContext);
     const location = useTorrentLocation();
+    const { setPath } = location;
     const [labels, setLabels] = useState<string[]>([]);
     const [start, setStart] = useState<boolean>(true);
     const [sequential, setSequential] = useState<boolean>(false);
@@ -124,8 +135,14 @@ function useCommonProps(opened: boolean) {
                     config.values.interface.addTorrentPriority) - 1) as PriorityNumberType);
             }
             setSequential(config.values.interface.addTorrentSequential);
+            if (options !== undefined) {
+                if (options.downloadDir !== undefined) setPath(options.downloadDir);
+                if (options.labels !== undefined) setLabels(options.labels);
+                if (options.paused !== undefined) setStart(!options.paused);
+                if (options.priority !== undefined) setPriority(options.priority);
+            }
         }
-    }, [config, opened]);
+    }, [config, opened, options, setPath]);
 
     const props = useMemo<AddCommonProps>(() => ({
         opened,
@@ -225,7 +242,7 @@ export function AddMagnet(props: AddCommonModalProps) {
         }
     }, [serverData, props.serverName, magnetData]);
 
-    const common = useCommonProps(props.opened);
+    const common = useCommonProps(props.opened, props.options);
     const { close } = props;
     const addMutation = useAddTorrent(
         // eslint-disable-next-line @typescript-eslint/no-explicit-any
@@ -448,11 +465,52 @@ async function readLocalTorrent(file: File): Promise<string> {
     });
 }
 
+interface BatchReadResult {
+    torrents: TorrentFileData[],
+    duplicates: Array<{
+        torrentPath: string,
+        name: string,
+        hash: string,
+        onServer: boolean,
+    }>,
+    errors: Array<{
+        torrentPath: string,
+        error: string,
+    }>,
+}
+
+async function readFiles(paths: string[], serverUrl: string) {
+    const result = await invoke<BatchReadResult>("read_files", { paths, serverUrl });
+    if (result.duplicates.length > 0) {
+        notifications.show({
+            title: `Skipped ${result.duplicates.length} duplicate torrents`,
+            message: result.duplicates.map((d) => d.onServer ? d.name : `${d.name} (repeated)`).join(", "),
+            color: "yellow",
+        });
+    }
+    if (result.errors.length > 0) {
+        notifications.show({
+            title: `Unable to read ${result.errors.length} torrents`,
+            message: result.errors.map((e) => e.error).join(", "),
+            color: "red",
+        });
+    }
+    return result.torrents.length > 0 ? result.torrents : undefined;
+}
+
+function localPath(uri: string) {
+    return uri.startsWith("file://") ? decodeURIComponent(uri.substring(7)) : uri;
+}
+
 function useTauriReadFile(
     props: AddCommonModalProps,
     torrentData: TorrentFileData[] | undefined,
     setTorrentData: React.Dispatch<React.SetStateAction<TorrentFileData[] | undefined>>,
 ) {
+    const serverConfig = useContext(ServerConfigContext);
+    const serverUrl = serverConfig.connection.url;
+    const serverName = serverConfig.name;
+
     useEffect(() => {
         if (TAURI && props.opened && torrentData === undefined) {
             const readFile = async (path: string | string[] | null) => {
@@ -460,18 +518,19 @@ function useTauriReadFile(
                     return undefined;
                 }
                 if (Array.isArray(path)) {
-                    return await Promise.all(path.map(
-                        async (p) => await invoke<TorrentFileData>("read_file", { path: p })));
+                    return await readFiles(path, serverUrl);
+                }
+                if (path.startsWith("http://") || path.startsWith("https://")) {
+                    return [await invoke<TorrentFileData>("fetch_torrent", { url: path, server: serverName })];
                 }
                 return [await invoke<TorrentFileData>("read_file", { path })];
             };
 
             let uri = props.uri;
-            if (typeof uri === "string" && uri.startsWith("file://")) {
-                uri = decodeURIComponent(uri.substring(7));
-            }
+            if (typeof uri === "string") uri = localPath(uri);
+            else if (Array.isArray(uri)) uri = uri.map(localPath);
 
-            const pathPromise = typeof uri === "string"
+            const pathPromise = (typeof uri === "string" || Array.isArray(uri))
                 ? Promise.resolve(uri)
                 : dialogOpen({
                     title: "Select torrent file",
@@ -497,7 +556,7 @@ function useTauriReadFile(
                     props.close();
                 });
         }
-    }, [props, setTorrentData, torrentData]);
+    }, [props, serverName, serverUrl, setTorrentData, torrentData]);
 }
 
 function useWebappReadFile(
@@ -598,7 +657,7 @@ interface TorrentFileData {
 export function AddTorrent(props: AddCommonModalProps) {
     const config = useContext(ConfigContext);
     const serverData = useServerTorrentData();
-    const common = useCommonProps(props.opened);
+    const common = useCommonProps(props.opened, props.options);
     const [torrentData, setTorrentData] = useState<TorrentFileData[]>();
     const [torrentName, setTorrentName] = useState("");
 
@@ -688,7 +747,7 @@ export function AddTorrent(props: AddCommonModalProps) {
                 }
             }
             if (TAURI && config.values.app.deleteAdded && vars.filePath !== undefined) {
-                void invoke("remove_file", { path: vars.filePath });
+                invoke("remove_file", { path: vars.filePath }).catch(console.error);
             }
         }, [config.values.app.deleteAdded, pathMutation]),
         useCallback((e) => {
@@ -737,7 +796,7 @@ export function AddTorrent(props: AddCommonModalProps) {
                     },
                     onSettled: () => {
                         if (TAURI && config.values.app.deleteAdded && torrentData[0].torrentPath !== undefined) {
-                            void invoke("remove_file", { path: torrentData[0].torrentPath });
+                            invoke("remove_file", { path: torrentData[0].torrentPath }).catch(console.error);
                         }
                     },
                 },
@@ -23,7 +23,7 @@ import { useForm } from "@mantine/form";
 import { useMutateTorrent, useTorrentDetails } from "queries";
 import { notifications } from "@mantine/notifications";
 import { Checkbox, Grid, LoadingOverlay, NumberInput } from "@mantine/core";
-import { useServerRpcVersion, useServerSelectedTorrents, useServerTorrentData } from "rpc/torrent";
+import { NoPeerLookups, useServerRpcVersion, useServerSelectedTorrents, useServerTorrentData } from "rpc/torrent";
 
 interface FormValues {
     downloadLimited?: boolean,
@@ -52,7 +52,7 @@ export function EditTorrent(props: ModalState) {
     }, [selected, serverData]);
 
     const { data: torrent, isLoading } = useTorrentDetails(
-        torrentId ?? -1, torrentId !== undefined && props.opened, false, true);
+        torrentId ?? -1, torrentId !== undefined && props.opened, NoPeerLookups, true);
 
     const form = useForm<FormValues>({});
 
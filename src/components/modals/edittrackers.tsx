@@ -25,7 +25,7 @@ import { notifications } from "@mantine/notifications";
 import { Button, Grid, LoadingOverlay, Text, Textarea } from "@mantine/core";
 import { ConfigContext } from "config";
 import type { TrackerStats } from "rpc/torrent";
-import { useServerRpcVersion, useServerSelectedTorrents, useServerTorrentData } from "rpc/torrent";
+import { NoPeerLookups, useServerRpcVersion, useServerSelectedTorrents, useServerTorrentData } from "rpc/torrent";
 
 interface FormValues {
     trackerList: string,
@@ -45,7 +45,7 @@ export function EditTrackers(props: ModalState) {
     }, [selected, serverData]);
 
     const { data: torrent, isLoading } = useTorrentDetails(
-        torrentId ?? -1, torrentId !== undefined && props.opened, false, true);
+        torrentId ?? -1, torrentId !== undefined && props.opened, NoPeerLookups, true);
 
     const form = useForm<FormValues>({});
 
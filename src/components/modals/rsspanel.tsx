@@ -0,0 +1,254 @@
+/**
+ * TrguiNG - next gen remote GUI for transmission torrent daemon
+ * Copyright (C) 2023  qu1ck (mail at qu1ck.org)
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+import React, { useCallback, useContext, useMemo, useState } from "react";
+import {
+    ActionIcon, Badge, Box, Button, Checkbox, Grid, Group, NumberInput, Select, Stack, TagsInput, Text, TextInput,
+} from "@mantine/core";
+import type { UseFormReturnType } from "@mantine/form";
+import { notifications } from "@mantine/notifications";
+import type { RssFeed, RssRule, RssSettings } from "config";
+import { ConfigContext } from "config";
+import { timestampToDateString } from "trutil";
+import * as Icon from "react-bootstrap-icons";
+import classes from "./settings.module.css";
+const { invoke } = await import(/* webpackChunkName: "taurishim" */"taurishim");
+
+export interface RssFormValues {
+    app: {
+        rss: RssSettings,
+    },
+}
+
+interface FeedTestItem {
+    title: string,
+    link: string,
+    rule?: string,
+    episode?: string,
+    inHistory: boolean,
+}
+
+interface HistoryEntry {
+    key: string,
+    title: string,
+    feed: string,
+    rule: string,
+    added: number,
+    episode?: string,
+}
+
+const NewFeed: RssFeed = { enabled: true, name: "", url: "", intervalMinutes: 30 };
+
+const NewRule: RssRule = {
+    enabled: true,
+    name: "",
+    feeds: [],
+    mustContain: "",
+    mustNotContain: "",
+    episodes: "",
+    server: "",
+    downloadDir: "",
+    labels: [],
+    paused: false,
+};
+
+function FeedTestResult({ items }: { items: FeedTestItem[] }) {
+    return (
+        <Box mah="12rem" className="scrollable">
+            {items.length === 0 && <Text fz="sm" c="dimmed">Feed has no items</Text>}
+            {items.map((item, i) =>
+                <Group key={i} gap="xs" wrap="nowrap">
+                    {item.rule !== undefined
+                        ? <Badge size="sm" color={item.inHistory ? "gray" : "green"}>{item.rule}</Badge>
+                        : <Badge size="sm" variant="outline" color="gray">no match</Badge>}
+                    {item.episode !== undefined && <Badge size="sm" variant="light">{item.episode}</Badge>}
+                    <Text fz="sm" truncate title={item.link}>{item.title}</Text>
+                </Group>)}
+        </Box>
+    );
+}
+
+function RssHistory() {
+    const config = useContext(ConfigContext);
+    const [history, setHistory] = useState<HistoryEntry[]>();
+
+    const load = useCallback(() => {
+        invoke<HistoryEntry[]>("rss_history").then(setHistory).catch(console.error);
+    }, []);
+
+    const clear = useCallback(() => {
+        invoke("clear_rss_history")
+            .then(load)
+            .catch((e) => {
+                notifications.show({ color: "red", title: "Unable to clear history", message: String(e) });
+            });
+    }, [load]);
+
+    return <>
+        <Group>
+            <Button variant="light" onClick={load}>Show downloaded</Button>
+            <Button variant="light" onClick={clear}>Clear history</Button>
+        </Group>
+        {history !== undefined && <Box mah="12rem" className="scrollable">
+            {history.length === 0 && <Text fz="sm" c="dimmed">Nothing was downloaded yet</Text>}
+            {history.map((entry) =>
+                <Group key={entry.key} gap="xs" wrap="nowrap">
+                    <Text fz="sm" c="dimmed" style={{ whiteSpace: "nowrap" }}>
+                        {timestampToDateString(entry.added, config)}
+                    </Text>
+                    <Badge size="sm">{entry.rule}</Badge>
+                    <Text fz="sm" truncate>{entry.title}</Text>
+                </Group>)}
+        </Box>}
+    </>;
+}
+
+export function RssPanel<V extends RssFormValues>(props: { form: UseFormReturnType<V>, servers: string[] }) {
+    const form = props.form as unknown as UseFormReturnType<RssFormValues>;
+    const { feeds, rules } = form.values.app.rss;
+    const [testResult, setTestResult] = useState<{ index: number, items: FeedTestItem[] }>();
+    const [testing, setTesting] = useState<number>();
+
+    const serverNames = useMemo(
+        () => [{ value: "", label: "Active server" }, ...props.servers.map((s) => ({ value: s, label: s }))],
+        [props.servers]);
+    const feedNames = useMemo(() => feeds.map((f) => f.name).filter((n) => n !== ""), [feeds]);
+
+    const testFeed = useCallback((index: number) => {
+        setTesting(index);
+        invoke<FeedTestItem[]>("test_rss_feed", { feed: feeds[index], rules })
+            .then((items) => { setTestResult({ index, items }); })
+            .catch((e) => {
+                setTestResult(undefined);
+                notifications.show({ color: "red", title: "Feed test failed", message: String(e) });
+            })
+            .finally(() => { setTesting(undefined); });
+    }, [feeds, rules]);
+
+    return (
+        <Stack>
+            <Text fz="sm" fs="italic">
+                Feeds are checked in the background, also when the main window is closed.
+                Each new item is added by the first enabled rule that matches its title.
+                Patterns are case insensitive regular expressions. Episode filter lists seasons
+                and episodes like 1x1-12;2x1- and needs S01E02 or 1x02 in the title,
+                every episode is then downloaded only once.
+            </Text>
+            <Text fw="bold">Feeds</Text>
+            {feeds.map((feed, i) => {
+                const prefix = `app.rss.feeds.${i}`;
+                return <Grid key={i} align="center" gutter="xs" className={classes.listEntry}>
+                    <Grid.Col span={1}>
+                        <Checkbox {...form.getInputProps(`${prefix}.enabled`, { type: "checkbox" })} />
+                    </Grid.Col>
+                    <Grid.Col span={3}>
+                        <TextInput placeholder="Name" {...form.getInputProps(`${prefix}.name`)} />
+                    </Grid.Col>
+                    <Grid.Col span={8}>
+                        <TextInput placeholder="https://example.com/rss" {...form.getInputProps(`${prefix}.url`)} />
+                    </Grid.Col>
+                    <Grid.Col span={3}>Check every</Grid.Col>
+                    <Grid.Col span={3}>
+                        <NumberInput min={5} rightSection="min" rightSectionWidth="3rem"
+                            {...form.getInputProps(`${prefix}.intervalMinutes`)} />
+                    </Grid.Col>
+                    <Grid.Col span={5}>
+                        <Button variant="subtle" loading={testing === i} disabled={feed.url === ""}
+                            onClick={() => { testFeed(i); }}>
+                            Test
+                        </Button>
+                    </Grid.Col>
+                    <Grid.Col span={1}>
+                        <ActionIcon variant="light" onClick={() => {
+                            setTestResult(undefined);
+                            form.removeListItem("app.rss.feeds", i);
+                        }}>
+                            <Icon.DashSquare size="1.2rem" color="royalblue" />
+                        </ActionIcon>
+                    </Grid.Col>
+                    {testResult?.index === i && <Grid.Col span={12}>
+                        <FeedTestResult items={testResult.items} />
+                    </Grid.Col>}
+                </Grid>;
+            })}
+            <Group>
+                <Button variant="light" leftSection={<Icon.PlusSquare size="1rem" />}
+                    onClick={() => { form.insertListItem("app.rss.feeds", { ...NewFeed }); }}>
+                    Add feed
+                </Button>
+            </Group>
+            <Text fw="bold">Rules</Text>
+            {rules.map((rule, i) => {
+                const prefix = `app.rss.rules.${i}`;
+                return <Grid key={i} align="center" gutter="xs" className={classes.listEntry}>
+                    <Grid.Col span={1}>
+                        <Checkbox {...form.getInputProps(`${prefix}.enabled`, { type: "checkbox" })} />
+                    </Grid.Col>
+                    <Grid.Col span={10}>
+                        <TextInput placeholder="Rule name" {...form.getInputProps(`${prefix}.name`)} />
+                    </Grid.Col>
+                    <Grid.Col span={1}>
+                        <ActionIcon variant="light" onClick={() => { form.removeListItem("app.rss.rules", i); }}>
+                            <Icon.DashSquare size="1.2rem" color="royalblue" />
+                        </ActionIcon>
+                    </Grid.Col>
+                    <Grid.Col span={3}>Feeds</Grid.Col>
+                    <Grid.Col span={9}>
+                        <TagsInput data={feedNames} placeholder={rule.feeds.length === 0 ? "All feeds" : undefined}
+                            {...form.getInputProps(`${prefix}.feeds`)} />
+                    </Grid.Col>
+                    <Grid.Col span={3}>Must contain</Grid.Col>
+                    <Grid.Col span={9}>
+                        <TextInput {...form.getInputProps(`${prefix}.mustContain`)} />
+                    </Grid.Col>
+                    <Grid.Col span={3}>Must not contain</Grid.Col>
+                    <Grid.Col span={9}>
+                        <TextInput {...form.getInputProps(`${prefix}.mustNotContain`)} />
+                    </Grid.Col>
+                    <Grid.Col span={3}>Episodes</Grid.Col>
+                    <Grid.Col span={9}>
+                        <TextInput placeholder="Any" {...form.getInputProps(`${prefix}.episodes`)} />
+                    </Grid.Col>
+                    <Grid.Col span={3}>Server</Grid.Col>
+                    <Grid.Col span={9}>
+                        <Select data={serverNames} {...form.getInputProps(`${prefix}.server`)} />
+                    </Grid.Col>
+                    <Grid.Col span={3}>Download directory</Grid.Col>
+                    <Grid.Col span={9}>
+                        <TextInput placeholder="Server default" {...form.getInputProps(`${prefix}.downloadDir`)} />
+                    </Grid.Col>
+                    <Grid.Col span={3}>Labels</Grid.Col>
+                    <Grid.Col span={6}>
+                        <TagsInput {...form.getInputProps(`${prefix}.labels`)} />
+                    </Grid.Col>
+                    <Grid.Col span={3}>
+                        <Checkbox label="Paused" {...form.getInputProps(`${prefix}.paused`, { type: "checkbox" })} />
+                    </Grid.Col>
+                </Grid>;
+            })}
+            <Group>
+                <Button variant="light" leftSection={<Icon.PlusSquare size="1rem" />}
+                    onClick={() => { form.insertListItem("app.rss.rules", { ...NewRule }); }}>
+                    Add rule
+                </Button>
+            </Group>
+            <Text fw="bold">History</Text>
+            <RssHistory />
+        </Stack>
+    );
+}
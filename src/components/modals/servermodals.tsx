@@ -17,15 +17,17 @@
  */
 
 import { useDisclosure } from "@mantine/hooks";
-import React, { memo, useCallback, useEffect, useImperativeHandle, useState } from "react";
+import React, { memo, useCallback, useContext, useEffect, useImperativeHandle, useRef, useState } from "react";
 import { EditLabelsModal } from "./editlabels";
 import { RemoveModal } from "./remove";
 import { MoveModal } from "./move";
+import type { AddOptions } from "./add";
 import { AddMagnet, AddTorrent } from "./add";
 import { DaemonSettingsModal } from "./daemon";
 import { EditTorrent } from "./edittorrent";
 import type { ServerTabsRef } from "components/servertabs";
 import { EditTrackers } from "./edittrackers";
+import { ConfigContext } from "config";
 const { TAURI, appWindow } = await import(/* webpackChunkName: "taurishim" */"taurishim");
 
 export interface ModalCallbacks {
@@ -61,6 +63,15 @@ function usePausingModalState(runUpdates: (run: boolean) => void): [boolean, ()
     return [opened, pauseOpen, closeResume];
 }
 
+interface AppArgs extends AddOptions {
+    torrents: string[],
+    server?: string,
+}
+
+function isTorrentFile(torrent: string) {
+    return !torrent.startsWith("magnet:") && (torrent.startsWith("file://") || !torrent.includes("://"));
+}
+
 const urlAddParam = new URLSearchParams(window.location.search).get("add");
 if (urlAddParam != null && urlAddParam !== "") {
     const newUrl = new URL(window.location.href);
@@ -91,11 +102,13 @@ const ServerModals = React.forwardRef<ModalCallbacks, ServerModalsProps>(functio
     }));
 
     const [magnetLink, setMagnetLink] = useState<string>();
-    const [torrent, setTorrent] = useState<string | File>();
+    const [torrent, setTorrent] = useState<string | string[] | File>();
 
-    const [addQueue, setAddQueue] = useState<Array<string | File>>(urlAddParam != null ? [urlAddParam] : []);
+    // Array entries are torrent files that are added together
+    const [addQueue, setAddQueue] = useState<Array<string | string[] | File>>(urlAddParam != null ? [urlAddParam] : []);
+    const addOptions = useRef(new Map<string, AddOptions>());
 
-    const enqueue = useCallback((paths: string[] | File[]) => {
+    const enqueue = useCallback((paths: Array<string | string[]> | File[]) => {
         setAddQueue([...addQueue, ...paths]);
         void appWindow.show();
         void appWindow.unminimize();
@@ -108,7 +121,8 @@ const ServerModals = React.forwardRef<ModalCallbacks, ServerModalsProps>(functio
             const dropResult = appWindow.onDragDropEvent((event) => {
                 if (event.payload.type === "drop") {
                     const files = event.payload.paths.filter((path) => path.toLowerCase().endsWith(".torrent"));
-                    if (files.length > 0) enqueue(files);
+                    if (files.length > 1) enqueue([files]);
+                    else if (files.length > 0) enqueue(files);
                 }
             });
             return () => {
@@ -138,11 +152,28 @@ const ServerModals = React.forwardRef<ModalCallbacks, ServerModalsProps>(functio
         }
     }, [enqueue]);
 
+    const config = useContext(ConfigContext);
+    const { serverName, tabsRef } = props;
+
     useEffect(() => {
         const listenResult = appWindow.listen<string>("app-arg", (event) => {
-            const args = JSON.parse(event.payload) as string[];
+            const payload = JSON.parse(event.payload) as string[] | AppArgs;
+            const args: AppArgs = Array.isArray(payload) ? { torrents: payload } : payload;
             console.log("Got app-arg:", args);
-            enqueue(args);
+            const { torrents, server, ...options } = args;
+            if (server !== undefined && server !== serverName && tabsRef.current != null
+                && config.getServer(server) !== undefined) {
+                const tabIndex = tabsRef.current.getOpenTabs().findIndex((tab) => tab === server);
+                if (tabIndex >= 0) tabsRef.current.switchTab(tabIndex);
+                else tabsRef.current.openTab(server);
+            }
+            torrents.forEach((t) => addOptions.current.set(t, options));
+            const files = torrents.filter(isTorrentFile);
+            if (files.length > 1) {
+                enqueue([...torrents.filter((t) => !isTorrentFile(t)), files]);
+            } else {
+                enqueue(torrents);
+            }
         }).then((unlisten) => {
             void appWindow.emit("listener-start", {});
             return unlisten;
@@ -153,7 +184,7 @@ const ServerModals = React.forwardRef<ModalCallbacks, ServerModalsProps>(functio
                 unlisten();
             });
         };
-    }, [enqueue]);
+    }, [config, enqueue, serverName, tabsRef]);
 
     useEffect(() => {
         if (addQueue.length > 0 && !showAddMagnetModal && !showAddTorrentModal) {
@@ -171,6 +202,7 @@ const ServerModals = React.forwardRef<ModalCallbacks, ServerModalsProps>(functio
     const closeAddMagnetModalAndPop = useCallback(() => {
         closeAddMagnetModal();
         if (magnetLink !== undefined && addQueue.length > 0 && addQueue[0] === magnetLink) {
+            addOptions.current.delete(magnetLink);
             setMagnetLink(undefined);
             setAddQueue(addQueue.slice(1));
         } else if (addQueue.length > 0) {
@@ -182,6 +214,8 @@ const ServerModals = React.forwardRef<ModalCallbacks, ServerModalsProps>(functio
     const closeAddTorrentModalAndPop = useCallback(() => {
         closeAddTorrentModal();
         if (torrent !== undefined && addQueue.length > 0 && addQueue[0] === torrent) {
+            if (typeof torrent === "string") addOptions.current.delete(torrent);
+            else if (Array.isArray(torrent)) torrent.forEach((t) => addOptions.current.delete(t));
             setTorrent(undefined);
             setAddQueue(addQueue.slice(1));
         } else if (addQueue.length > 0) {
@@ -197,6 +231,7 @@ const ServerModals = React.forwardRef<ModalCallbacks, ServerModalsProps>(functio
         <AddMagnet
             serverName={props.serverName}
             uri={magnetLink}
+            options={magnetLink !== undefined ? addOptions.current.get(magnetLink) : undefined}
             tabsRef={props.tabsRef}
             opened={showAddMagnetModal}
             close={closeAddMagnetModalAndPop}
@@ -204,6 +239,9 @@ const ServerModals = React.forwardRef<ModalCallbacks, ServerModalsProps>(functio
         <AddTorrent
             serverName={props.serverName}
             uri={torrent}
+            options={typeof torrent === "string"
+                ? addOptions.current.get(torrent)
+                : Array.isArray(torrent) ? addOptions.current.get(torrent[0]) : undefined}
             tabsRef={props.tabsRef}
             opened={showAddTorrentModal}
             close={closeAddTorrentModalAndPop}
@@ -17,13 +17,15 @@
  */
 
 import {
-    ActionIcon, Box, Button, Checkbox, Flex, Grid, Group, PasswordInput, SegmentedControl,
-    Stack, Switch, Tabs, Text, Textarea, TextInput,
+    ActionIcon, Box, Button, Checkbox, Flex, Grid, Group, NumberInput, PasswordInput, SegmentedControl,
+    Select, Stack, Switch, Tabs, Text, Textarea, TextInput,
 } from "@mantine/core";
 import classes from "./settings.module.css";
 import clsx from "clsx";
-import type { ServerConfig, WindowCloseOption, WindowMinimizeOption } from "config";
-import { ConfigContext, WindowCloseOptions, WindowMinimizeOptions } from "config";
+import type {
+    DownloadSite, GeoIpSettings, RssSettings, ProxyType, ServerConfig, TimeoutSettings, WatchFolder, WindowCloseOption, WindowMinimizeOption,
+} from "config";
+import { ConfigContext, ProxyTypes, WatchFolderAfterAddOptions, WindowCloseOptions, WindowMinimizeOptions } from "config";
 import React, { useCallback, useContext, useEffect, useMemo, useState } from "react";
 import type { ModalState } from "./common";
 import { SaveCancelModal } from "./common";
@@ -33,9 +35,13 @@ import { useForm } from "@mantine/form";
 import { UAParser } from "ua-parser-js";
 import type { InterfaceFormValues } from "./interfacepanel";
 import { InterfaceSettigsPanel } from "./interfacepanel";
+import type { RssFormValues } from "./rsspanel";
+import { RssPanel } from "./rsspanel";
+import { notifications } from "@mantine/notifications";
+import { timestampToDateString } from "trutil";
 const { TAURI, invoke } = await import(/* webpackChunkName: "taurishim" */"taurishim");
 
-interface FormValues extends InterfaceFormValues {
+interface FormValues extends InterfaceFormValues, RssFormValues {
     servers: ServerConfig[],
     app: {
         deleteAdded: boolean,
@@ -44,6 +50,11 @@ interface FormValues extends InterfaceFormValues {
         showTrayIcon: boolean,
         onMinimize: WindowMinimizeOption,
         onClose: WindowCloseOption,
+        geoip: GeoIpSettings,
+        watchFolders: WatchFolder[],
+        clipboardMonitoring: boolean,
+        downloadSites: DownloadSite[],
+        rss: RssSettings,
     },
 }
 
@@ -80,7 +91,16 @@ function ServerListPanel({ form, current, setCurrent }: ServerListPanelProps) {
                     variant="light"
                     onClick={() => {
                         form.insertListItem("servers", {
-                            connection: { url: "", username: "", password: "", acceptInvalidCerts: false },
+                            connection: {
+                                url: "",
+                                username: "",
+                                password: "",
+                                acceptInvalidCerts: false,
+                                proxy: { type: "system", host: "", port: 1080, username: "", password: "" },
+                                tls: { caCert: "", clientCert: "", clientKey: "", clientCertPassword: "", pinnedSha256: "" },
+                                timeouts: { connect: 10, read: 40, total: 60 },
+                                http2: false,
+                            },
                             name: "new",
                             pathMappings: [],
                             expandedDirFilters: [],
@@ -139,6 +159,18 @@ interface ServerPanelProps {
 function ServerPanel(props: ServerPanelProps) {
     const [mappingsString, setMappingsString] = useState("");
     const server = props.form.values.servers[props.current];
+    const setTimeoutValue = useCallback((name: keyof TimeoutSettings, value: string | number) => {
+        props.form.setFieldValue(`servers.${props.current}.connection.timeouts`, {
+            connect: 10,
+            read: 40,
+            total: 60,
+            ...props.form.values.servers[props.current].connection.timeouts,
+            [name]: typeof value === "number" ? value : 0,
+        });
+    }, [props.form, props.current]);
+    const hasProxyServer = !["system", "none"].includes(server.connection.proxy?.type ?? "system");
+    // Pinned certificate is checked instead of validating the certificate chain
+    const isPinned = (server.connection.tls?.pinnedSha256 ?? "").trim() !== "";
 
     useEffect(() => {
         setMappingsString(server.pathMappings.map((m) => `${m.from}=${m.to}`).join("\n"));
@@ -166,10 +198,43 @@ function ServerPanel(props: ServerPanelProps) {
             <Checkbox
                 my="md"
                 label="Accept invalid SSL certificates"
+                description={isPinned ? "Not used with a pinned certificate" : undefined}
+                disabled={isPinned}
                 {...props.form.getInputProps(`servers.${props.current}.connection.acceptInvalidCerts`, { type: "checkbox" })}
             />
 
+            <Checkbox
+                my="md"
+                label="Allow HTTP/2"
+                {...props.form.getInputProps(`servers.${props.current}.connection.http2`, { type: "checkbox" })}
+            />
+
             <Grid>
+                <Grid.Col span={4}>
+                    <NumberInput
+                        label="Connect timeout (s)"
+                        min={0}
+                        value={server.connection.timeouts?.connect ?? 10}
+                        onChange={(value) => { setTimeoutValue("connect", value); }}
+                    />
+                </Grid.Col>
+                <Grid.Col span={4}>
+                    <NumberInput
+                        label="Read timeout (s)"
+                        min={0}
+                        value={server.connection.timeouts?.read ?? 40}
+                        onChange={(value) => { setTimeoutValue("read", value); }}
+                    />
+                </Grid.Col>
+                <Grid.Col span={4}>
+                    <NumberInput
+                        label="Total timeout (s)"
+                        min={0}
+                        value={server.connection.timeouts?.total ?? 60}
+                        onChange={(value) => { setTimeoutValue("total", value); }}
+                    />
+                </Grid.Col>
+
                 <Grid.Col span={6}>
                     <TextInput
                         label="User name"
@@ -187,6 +252,111 @@ function ServerPanel(props: ServerPanelProps) {
                     />
                 </Grid.Col>
 
+                <Grid.Col span={4}>
+                    <Select
+                        label="Proxy"
+                        data={ProxyTypes as unknown as string[]}
+                        value={server.connection.proxy?.type ?? "system"}
+                        onChange={(value) => {
+                            props.form.setFieldValue(`servers.${props.current}.connection.proxy`, {
+                                host: "",
+                                port: 1080,
+                                username: "",
+                                password: "",
+                                ...server.connection.proxy,
+                                type: (value ?? "system") as ProxyType,
+                            });
+                        }}
+                    />
+                </Grid.Col>
+                <Grid.Col span={5}>
+                    <TextInput
+                        label="Proxy host"
+                        disabled={!hasProxyServer}
+                        {...props.form.getInputProps(`servers.${props.current}.connection.proxy.host`)}
+                        autoComplete="off"
+                        autoCorrect="off"
+                        autoCapitalize="off"
+                        spellCheck="false"
+                    />
+                </Grid.Col>
+                <Grid.Col span={3}>
+                    <NumberInput
+                        label="Port"
+                        min={1}
+                        max={65535}
+                        disabled={!hasProxyServer}
+                        {...props.form.getInputProps(`servers.${props.current}.connection.proxy.port`)}
+                    />
+                </Grid.Col>
+                <Grid.Col span={6}>
+                    <TextInput
+                        label="Proxy user name"
+                        disabled={!hasProxyServer}
+                        {...props.form.getInputProps(`servers.${props.current}.connection.proxy.username`)}
+                        autoComplete="off"
+                        autoCorrect="off"
+                        autoCapitalize="off"
+                        spellCheck="false"
+                    />
+                </Grid.Col>
+                <Grid.Col span={6}>
+                    <PasswordInput
+                        label="Proxy password"
+                        disabled={!hasProxyServer}
+                        {...props.form.getInputProps(`servers.${props.current}.connection.proxy.password`)}
+                    />
+                </Grid.Col>
+
+                <Grid.Col span={12}>
+                    <TextInput
+                        label="CA certificate file (PEM)"
+                        description={isPinned ? "Not used with a pinned certificate" : undefined}
+                        disabled={isPinned}
+                        {...props.form.getInputProps(`servers.${props.current}.connection.tls.caCert`)}
+                        autoComplete="off"
+                        autoCorrect="off"
+                        autoCapitalize="off"
+                        spellCheck="false"
+                    />
+                </Grid.Col>
+                <Grid.Col span={6}>
+                    <TextInput
+                        label="Client certificate file (PEM or PKCS#12)"
+                        {...props.form.getInputProps(`servers.${props.current}.connection.tls.clientCert`)}
+                        autoComplete="off"
+                        autoCorrect="off"
+                        autoCapitalize="off"
+                        spellCheck="false"
+                    />
+                </Grid.Col>
+                <Grid.Col span={6}>
+                    {/\.(p12|pfx)$/i.test(server.connection.tls?.clientCert ?? "")
+                        ? <PasswordInput
+                            label="Certificate password"
+                            {...props.form.getInputProps(`servers.${props.current}.connection.tls.clientCertPassword`)}
+                        />
+                        : <TextInput
+                            label="Client key file (PEM)"
+                            {...props.form.getInputProps(`servers.${props.current}.connection.tls.clientKey`)}
+                            autoComplete="off"
+                            autoCorrect="off"
+                            autoCapitalize="off"
+                            spellCheck="false"
+                        />}
+                </Grid.Col>
+                <Grid.Col span={12}>
+                    <TextInput
+                        label="Pinned server certificate SHA-256 fingerprint"
+                        {...props.form.getInputProps(`servers.${props.current}.connection.tls.pinnedSha256`)}
+                        placeholder="Leave empty to validate the certificate normally"
+                        autoComplete="off"
+                        autoCorrect="off"
+                        autoCapitalize="off"
+                        spellCheck="false"
+                    />
+                </Grid.Col>
+
                 <Grid.Col span={12}>
                     <Textarea
                         label={"Path mappings in \"remote=local\" format, one per line"}
@@ -218,23 +388,25 @@ const bigSwitchStyles = { track: { flexGrow: 1 } };
 
 function IntegrationsPanel({ form }: { form: UseFormReturnType<FormValues> }) {
     const platform = useMemo(() => UAParser().os.name ?? "unknown", []);
+    const integrationsSupported = platform === "Windows" || platform === "Linux";
 
     const [autostart, setAutostart] = useState(false);
-
-    const associateTorrent = useCallback(() => {
-        void invoke("app_integration", { mode: "torrent" });
-    }, []);
-    const associateMagnet = useCallback(() => {
-        void invoke("app_integration", { mode: "magnet" });
-    }, []);
+    const [torrentAssociated, setTorrentAssociated] = useState(false);
+    const [magnetAssociated, setMagnetAssociated] = useState(false);
 
     useEffect(() => {
-        if (platform === "Windows") {
+        if (integrationsSupported) {
             invoke("app_integration", { mode: "getautostart" })
                 .then((result) => { setAutostart(result as boolean); })
                 .catch(console.error);
+            invoke("app_integration", { mode: "gettorrent" })
+                .then((result) => { setTorrentAssociated(result as boolean); })
+                .catch(console.error);
+            invoke("app_integration", { mode: "getmagnet" })
+                .then((result) => { setMagnetAssociated(result as boolean); })
+                .catch(console.error);
         }
-    }, [platform]);
+    }, [integrationsSupported]);
 
     const onChangeAutostart = useCallback((e: React.ChangeEvent<HTMLInputElement>) => {
         const state = e.target.checked;
@@ -242,6 +414,22 @@ function IntegrationsPanel({ form }: { form: UseFormReturnType<FormValues> }) {
         void invoke("app_integration", { mode: state ? "autostart" : "noautostart" });
     }, []);
 
+    const onChangeTorrentAssociation = useCallback((e: React.ChangeEvent<HTMLInputElement>) => {
+        const state = e.target.checked;
+        invoke("app_integration", { mode: state ? "torrent" : "notorrent" })
+            .then(async () => await invoke("app_integration", { mode: "gettorrent" }))
+            .then((result) => { setTorrentAssociated(result as boolean); })
+            .catch(console.error);
+    }, []);
+
+    const onChangeMagnetAssociation = useCallback((e: React.ChangeEvent<HTMLInputElement>) => {
+        const state = e.target.checked;
+        invoke("app_integration", { mode: state ? "magnet" : "nomagnet" })
+            .then(async () => await invoke("app_integration", { mode: "getmagnet" }))
+            .then((result) => { setMagnetAssociated(result as boolean); })
+            .catch(console.error);
+    }, []);
+
     return (
         <Grid align="center" gutter="md">
             <Grid.Col span={6}>Delete successfully added torrent files</Grid.Col>
@@ -275,7 +463,7 @@ function IntegrationsPanel({ form }: { form: UseFormReturnType<FormValues> }) {
                     {...form.getInputProps("app.toastNotificationSound", { type: "checkbox" })}
                 />
             </Grid.Col>
-            {platform === "Windows" && <>
+            {integrationsSupported && <>
                 <Grid.Col span={6}>Launch on startup</Grid.Col>
                 <Grid.Col span={2}>
                     <Switch
@@ -288,9 +476,30 @@ function IntegrationsPanel({ form }: { form: UseFormReturnType<FormValues> }) {
                     />
                 </Grid.Col>
                 <Grid.Col span={4}></Grid.Col>
-                <Grid.Col span={6}>Associate application</Grid.Col>
-                <Grid.Col span={3}><Button onClick={associateTorrent}>.torrent files</Button></Grid.Col>
-                <Grid.Col span={3}><Button onClick={associateMagnet}>magnet links</Button></Grid.Col>
+                <Grid.Col span={6}>Open .torrent files</Grid.Col>
+                <Grid.Col span={2}>
+                    <Switch
+                        onLabel="ON"
+                        offLabel="OFF"
+                        size="xl"
+                        styles={bigSwitchStyles}
+                        checked={torrentAssociated}
+                        onChange={onChangeTorrentAssociation}
+                    />
+                </Grid.Col>
+                <Grid.Col span={4}></Grid.Col>
+                <Grid.Col span={6}>Open magnet links</Grid.Col>
+                <Grid.Col span={2}>
+                    <Switch
+                        onLabel="ON"
+                        offLabel="OFF"
+                        size="xl"
+                        styles={bigSwitchStyles}
+                        checked={magnetAssociated}
+                        onChange={onChangeMagnetAssociation}
+                    />
+                </Grid.Col>
+                <Grid.Col span={4}></Grid.Col>
             </>}
             <Grid.Col span={6}>Show tray icon</Grid.Col>
             <Grid.Col span={2}>
@@ -305,11 +514,24 @@ function IntegrationsPanel({ form }: { form: UseFormReturnType<FormValues> }) {
                         if (!e.currentTarget.checked) {
                             form.setFieldValue("app.onMinimize", "minimize");
                             form.setFieldValue("app.onClose", "quit");
+                            form.setFieldValue("app.clipboardMonitoring", false);
                         }
                     }}
                 />
             </Grid.Col>
             <Grid.Col span={4}>(takes effect after restart)</Grid.Col>
+            <Grid.Col span={6}>Offer to add copied magnet links</Grid.Col>
+            <Grid.Col span={2}>
+                <Switch
+                    onLabel="ON"
+                    offLabel="OFF"
+                    size="xl"
+                    styles={bigSwitchStyles}
+                    disabled={!form.values.app.showTrayIcon}
+                    {...form.getInputProps("app.clipboardMonitoring", { type: "checkbox" })}
+                />
+            </Grid.Col>
+            <Grid.Col span={4}>(added from the tray menu)</Grid.Col>
             <Grid.Col span={6}>When minimized</Grid.Col>
             <Grid.Col span={6}>
                 <SegmentedControl
@@ -330,6 +552,8 @@ function IntegrationsPanel({ form }: { form: UseFormReturnType<FormValues> }) {
                 <Text fz="sm" fs="italic">
                     Hiding the window keeps frontend running, this uses more RAM but reopening the window is nearly instant.
                     Closing the window shuts down the webview, in this mode reopening the window is slower.
+                    With the webview closed polling, notifications and the tray status are handled by a lightweight
+                    background process which uses a fraction of the memory.
                     You can always access the window through the system tray icon.
                 </Text>
             </Grid.Col>
@@ -337,6 +561,238 @@ function IntegrationsPanel({ form }: { form: UseFormReturnType<FormValues> }) {
     );
 }
 
+interface GeoIpDbInfo {
+    kind: "country" | "asn" | "city",
+    path: string,
+    databaseType: string,
+    buildEpoch: number,
+}
+
+function GeoIpPanel({ form }: { form: UseFormReturnType<FormValues> }) {
+    const config = useContext(ConfigContext);
+    const [dbInfo, setDbInfo] = useState<GeoIpDbInfo[]>([]);
+    const [updating, setUpdating] = useState(false);
+
+    const refreshInfo = useCallback(() => {
+        invoke<GeoIpDbInfo[]>("geoip_info")
+            .then(setDbInfo)
+            .catch(console.error);
+    }, []);
+
+    useEffect(refreshInfo, [refreshInfo]);
+
+    const updateUrl = form.values.app.geoip.updateUrl;
+    const customCountryDb = form.values.app.geoip.countryDb !== "";
+    const onUpdate = useCallback(() => {
+        setUpdating(true);
+        invoke("update_geoip_db", { url: updateUrl })
+            .then(() => {
+                notifications.show({ color: "green", message: "GeoIP database updated" });
+            })
+            .catch((e) => {
+                notifications.show({ color: "red", title: "GeoIP database update failed", message: String(e) });
+            })
+            .finally(() => {
+                setUpdating(false);
+                refreshInfo();
+            });
+    }, [updateUrl, refreshInfo]);
+
+    return (
+        <Grid align="center" gutter="md">
+            <Grid.Col>
+                <Text fz="sm" fs="italic">
+                    Custom MaxMind DB (.mmdb) files can be used instead of the bundled ones.
+                    Leave the path empty to use the default database. Files are checked for changes every minute.
+                </Text>
+            </Grid.Col>
+            <Grid.Col span={3}>Country database</Grid.Col>
+            <Grid.Col span={9}>
+                <TextInput {...form.getInputProps("app.geoip.countryDb")} />
+            </Grid.Col>
+            <Grid.Col span={3}>ASN database</Grid.Col>
+            <Grid.Col span={9}>
+                <TextInput {...form.getInputProps("app.geoip.asnDb")} />
+            </Grid.Col>
+            <Grid.Col span={3}>City database</Grid.Col>
+            <Grid.Col span={9}>
+                <TextInput {...form.getInputProps("app.geoip.cityDb")} />
+            </Grid.Col>
+            <Grid.Col span={3}>Update url</Grid.Col>
+            <Grid.Col span={9}>
+                <TextInput
+                    placeholder="https://example.com/country.mmdb.gz"
+                    disabled={customCountryDb}
+                    {...form.getInputProps("app.geoip.updateUrl")} />
+            </Grid.Col>
+            <Grid.Col span={3}>Update every</Grid.Col>
+            <Grid.Col span={3}>
+                <NumberInput min={0} rightSection="days" rightSectionWidth="3rem"
+                    disabled={customCountryDb}
+                    {...form.getInputProps("app.geoip.updateIntervalDays")} />
+            </Grid.Col>
+            <Grid.Col span={6}>
+                <Button onClick={onUpdate} loading={updating} disabled={updateUrl === "" || customCountryDb}>
+                    Update now
+                </Button>
+            </Grid.Col>
+            <Grid.Col>
+                <Text fz="sm" fs="italic">
+                    Downloaded country database is saved in the application data directory,
+                    updates are disabled while a custom country database is set. Set interval to 0 to disable automatic updates.
+                </Text>
+            </Grid.Col>
+            {dbInfo.map((info) => <React.Fragment key={info.kind}>
+                <Grid.Col span={3}>Loaded {info.kind} database</Grid.Col>
+                <Grid.Col span={9}>
+                    <Text fz="sm">{info.databaseType}, built {timestampToDateString(info.buildEpoch, config)}</Text>
+                    <Text fz="xs" c="dimmed" style={{ wordBreak: "break-all" }}>{info.path}</Text>
+                </Grid.Col>
+            </React.Fragment>)}
+        </Grid>
+    );
+}
+
+function WatchFoldersPanel({ form }: { form: UseFormReturnType<FormValues> }) {
+    const serverNames = useMemo(
+        () => [{ value: "", label: "Active server" }, ...form.values.servers.map((s) => ({ value: s.name, label: s.name }))],
+        [form.values.servers]);
+
+    return (
+        <Stack>
+            <Text fz="sm" fs="italic">
+                New .torrent and .magnet files in watched folders are added in the background,
+                also when the main window is closed. Files that were added are deleted or moved away.
+            </Text>
+            {form.values.app.watchFolders.map((folder, i) => {
+                const prefix = `app.watchFolders.${i}`;
+                return <Grid key={i} align="center" gutter="xs" className={classes.listEntry}>
+                    <Grid.Col span={1}>
+                        <Checkbox {...form.getInputProps(`${prefix}.enabled`, { type: "checkbox" })} />
+                    </Grid.Col>
+                    <Grid.Col span={10}>
+                        <TextInput placeholder="Folder path" {...form.getInputProps(`${prefix}.path`)} />
+                    </Grid.Col>
+                    <Grid.Col span={1}>
+                        <ActionIcon variant="light" onClick={() => { form.removeListItem("app.watchFolders", i); }}>
+                            <Icon.DashSquare size="1.2rem" color="royalblue" />
+                        </ActionIcon>
+                    </Grid.Col>
+                    <Grid.Col span={3}>Server</Grid.Col>
+                    <Grid.Col span={9}>
+                        <Select data={serverNames} {...form.getInputProps(`${prefix}.server`)} />
+                    </Grid.Col>
+                    <Grid.Col span={3}>Download directory</Grid.Col>
+                    <Grid.Col span={9}>
+                        <TextInput placeholder="Server default" {...form.getInputProps(`${prefix}.downloadDir`)} />
+                    </Grid.Col>
+                    <Grid.Col span={3}>Labels</Grid.Col>
+                    <Grid.Col span={6}>
+                        <TextInput
+                            key={folder.labels.join(",")}
+                            placeholder="Comma separated"
+                            defaultValue={folder.labels.join(", ")}
+                            onBlur={(e) => {
+                                form.setFieldValue(`${prefix}.labels`, e.currentTarget.value
+                                    .split(",").map((l) => l.trim()).filter((l) => l !== ""));
+                            }} />
+                    </Grid.Col>
+                    <Grid.Col span={3}>
+                        <Checkbox label="Paused" {...form.getInputProps(`${prefix}.paused`, { type: "checkbox" })} />
+                    </Grid.Col>
+                    <Grid.Col span={3}>After adding</Grid.Col>
+                    <Grid.Col span={3}>
+                        <SegmentedControl
+                            data={WatchFolderAfterAddOptions as unknown as string[]}
+                            {...form.getInputProps(`${prefix}.afterAdd`)} />
+                    </Grid.Col>
+                    <Grid.Col span={6}>
+                        <TextInput
+                            placeholder="processed"
+                            disabled={folder.afterAdd !== "move"}
+                            {...form.getInputProps(`${prefix}.moveTo`)} />
+                    </Grid.Col>
+                </Grid>;
+            })}
+            <Group>
+                <Button
+                    variant="light"
+                    leftSection={<Icon.FolderPlus size="1rem" />}
+                    onClick={() => {
+                        form.insertListItem("app.watchFolders", {
+                            enabled: true,
+                            path: "",
+                            server: "",
+                            downloadDir: "",
+                            labels: [],
+                            paused: false,
+                            afterAdd: "delete",
+                            moveTo: "",
+                        });
+                    }}
+                >
+                    Add folder
+                </Button>
+            </Group>
+        </Stack>
+    );
+}
+
+function DownloadSitesPanel({ form }: { form: UseFormReturnType<FormValues> }) {
+    return (
+        <Stack>
+            <Text fz="sm" fs="italic">
+                Torrent file links of these sites and their subdomains are downloaded by this app
+                with the given cookie and headers instead of by the server, this is needed for
+                private trackers that require login.
+            </Text>
+            {form.values.app.downloadSites.map((site, i) => {
+                const prefix = `app.downloadSites.${i}`;
+                return <Grid key={i} align="center" gutter="xs" className={classes.listEntry}>
+                    <Grid.Col span={3}>Host</Grid.Col>
+                    <Grid.Col span={8}>
+                        <TextInput placeholder="tracker.example.com" {...form.getInputProps(`${prefix}.host`)} />
+                    </Grid.Col>
+                    <Grid.Col span={1}>
+                        <ActionIcon variant="light" onClick={() => { form.removeListItem("app.downloadSites", i); }}>
+                            <Icon.DashSquare size="1.2rem" color="royalblue" />
+                        </ActionIcon>
+                    </Grid.Col>
+                    <Grid.Col span={3}>Cookie</Grid.Col>
+                    <Grid.Col span={9}>
+                        <PasswordInput placeholder="uid=...; pass=..." {...form.getInputProps(`${prefix}.cookie`)} />
+                    </Grid.Col>
+                    <Grid.Col span={3}>Headers</Grid.Col>
+                    <Grid.Col span={9}>
+                        <Textarea
+                            key={site.headers.join("\n")}
+                            placeholder="Name: value, one per line"
+                            defaultValue={site.headers.join("\n")}
+                            onBlur={(e) => {
+                                form.setFieldValue(`${prefix}.headers`, e.currentTarget.value
+                                    .split("\n").map((h) => h.trim()).filter((h) => h.includes(":")));
+                            }}
+                            autosize
+                            minRows={2}
+                        />
+                    </Grid.Col>
+                </Grid>;
+            })}
+            <Group>
+                <Button
+                    variant="light"
+                    leftSection={<Icon.PlusSquare size="1rem" />}
+                    onClick={() => {
+                        form.insertListItem("app.downloadSites", { host: "", cookie: "", headers: [] });
+                    }}
+                >
+                    Add site
+                </Button>
+            </Group>
+        </Stack>
+    );
+}
+
 interface AppSettingsModalProps extends ModalState {
     onSave: (servers: ServerConfig[]) => void,
 }
@@ -399,6 +855,14 @@ export function AppSettingsModal(props: AppSettingsModalProps) {
             config.values.app = { ...config.values.app, ...form.values.app };
             config.values.interface = { ...config.values.interface, ...form.values.interface };
             config.cleanup();
+            if (TAURI) {
+                void invoke("set_geoip_config", { config: config.values.app.geoip });
+                void invoke("set_watch_folders", { folders: config.values.app.watchFolders });
+                void invoke("set_clipboard_monitoring", {
+                    enabled: config.values.app.clipboardMonitoring && config.values.app.showTrayIcon,
+                });
+                void invoke("set_rss_config", { config: config.values.app.rss });
+            }
             props.onSave(form.values.servers);
             props.close();
         }
@@ -419,6 +883,10 @@ export function AppSettingsModal(props: AppSettingsModalProps) {
                         <Tabs.Tab value="servers" p="lg">Servers</Tabs.Tab>
                         <Tabs.Tab value="integrations" p="lg">Integrations</Tabs.Tab>
                         {TAURI && <Tabs.Tab value="interface" p="lg">Interface</Tabs.Tab>}
+                        {TAURI && <Tabs.Tab value="geoip" p="lg">GeoIP</Tabs.Tab>}
+                        {TAURI && <Tabs.Tab value="watch" p="lg">Watch folders</Tabs.Tab>}
+                        {TAURI && <Tabs.Tab value="sites" p="lg">Sites</Tabs.Tab>}
+                        {TAURI && <Tabs.Tab value="rss" p="lg">RSS</Tabs.Tab>}
                     </Tabs.List>
 
                     <Tabs.Panel value="servers" pt="md">
@@ -437,6 +905,22 @@ export function AppSettingsModal(props: AppSettingsModalProps) {
                     {TAURI && <Tabs.Panel value="interface" pt="md">
                         <InterfaceSettigsPanel form={form} />
                     </Tabs.Panel>}
+
+                    {TAURI && <Tabs.Panel value="geoip" pt="md">
+                        <GeoIpPanel form={form} />
+                    </Tabs.Panel>}
+
+                    {TAURI && <Tabs.Panel value="watch" pt="md">
+                        <WatchFoldersPanel form={form} />
+                    </Tabs.Panel>}
+
+                    {TAURI && <Tabs.Panel value="sites" pt="md">
+                        <DownloadSitesPanel form={form} />
+                    </Tabs.Panel>}
+
+                    {TAURI && <Tabs.Panel value="rss" pt="md">
+                        <RssPanel form={form} servers={form.values.servers.map((s) => s.name)} />
+                    </Tabs.Panel>}
                 </Tabs>
             </form>
         </SaveCancelModal>
@@ -55,7 +55,14 @@ const AllFields: TableField[] = [
     { name: "cachedStatus", label: "Status" },
 ];
 
-if (TAURI) AllFields.splice(1, 0, { name: "cachedCountryName", label: "Country", columnId: "country", component: CountryField });
+if (TAURI) {
+    AllFields.splice(1, 0,
+        { name: "cachedCountryName", label: "Country", columnId: "country", component: CountryField },
+        { name: "cachedLocation", label: "Location" },
+        { name: "cachedOrganization", label: "ISP" },
+        { name: "cachedHostname", label: "Host name" },
+    );
+}
 
 function CountryField(props: TableFieldProps) {
     const iso = props.entry.cachedCountryIso;
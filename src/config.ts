@@ -24,11 +24,40 @@ import type { MantineColorScheme, DefaultMantineColor } from "@mantine/core";
 import type { PriorityNumberType } from "rpc/transmission";
 const { readConfigText, writeConfigText } = await import(/* webpackChunkName: "taurishim" */"taurishim");
 
+export const ProxyTypes = ["system", "none", "http", "https", "socks5"] as const;
+export type ProxyType = typeof ProxyTypes[number];
+
+export interface ProxySettings {
+    type: ProxyType,
+    host: string,
+    port: number,
+    username: string,
+    password: string,
+}
+
+export interface TlsSettings {
+    caCert: string,
+    clientCert: string,
+    clientKey: string,
+    clientCertPassword: string,
+    pinnedSha256: string,
+}
+
+export interface TimeoutSettings {
+    connect: number,
+    read: number,
+    total: number,
+}
+
 export interface ServerConnection {
     url: string,
     username: string,
     password: string,
     acceptInvalidCerts?: boolean,
+    proxy?: ProxySettings,
+    tls?: TlsSettings,
+    timeouts?: TimeoutSettings,
+    http2?: boolean,
 }
 
 export interface PathMapping {
@@ -91,7 +120,7 @@ export type SectionsVisibility<S extends string> = Array<{
     visible: boolean,
 }>;
 
-export const WindowMinimizeOptions = ["minimize", "hide"] as const;
+export const WindowMinimizeOptions = ["minimize", "hide", "close"] as const;
 export const WindowCloseOptions = ["hide", "close", "quit"] as const;
 export const DeleteTorrentDataOptions = ["default off", "default on", "remember selection"] as const;
 export const AddTorrentStartOptions = DeleteTorrentDataOptions;
@@ -132,6 +161,60 @@ export interface StyleOverrides {
     backgroundColor?: ColorSetting, // deprecated
 }
 
+export interface GeoIpSettings {
+    countryDb: string,
+    asnDb: string,
+    cityDb: string,
+    updateUrl: string,
+    updateIntervalDays: number,
+}
+
+export const WatchFolderAfterAddOptions = ["delete", "move"] as const;
+export type WatchFolderAfterAddOption = typeof WatchFolderAfterAddOptions[number];
+
+export interface WatchFolder {
+    enabled: boolean,
+    path: string,
+    server: string, // empty for the active server
+    downloadDir: string,
+    labels: string[],
+    paused: boolean,
+    afterAdd: WatchFolderAfterAddOption,
+    moveTo: string, // defaults to "processed" subfolder
+}
+
+// Cookie and headers sent when downloading .torrent files from the site
+export interface DownloadSite {
+    host: string,
+    cookie: string,
+    headers: string[],
+}
+
+export interface RssFeed {
+    enabled: boolean,
+    name: string,
+    url: string,
+    intervalMinutes: number,
+}
+
+export interface RssRule {
+    enabled: boolean,
+    name: string,
+    feeds: string[], // empty for all feeds
+    mustContain: string,
+    mustNotContain: string,
+    episodes: string, // e.g. "1x1-12;2x1-"
+    server: string, // empty for the active server
+    downloadDir: string,
+    labels: string[],
+    paused: boolean,
+}
+
+export interface RssSettings {
+    feeds: RssFeed[],
+    rules: RssRule[],
+}
+
 interface Settings {
     servers: ServerConfig[],
     openTabs?: string[], // moved into app
@@ -151,6 +234,11 @@ interface Settings {
         onClose: WindowCloseOption,
         fontSizeBase: number,
         fontSize: number,
+        geoip: GeoIpSettings,
+        watchFolders: WatchFolder[],
+        clipboardMonitoring: boolean,
+        downloadSites: DownloadSite[],
+        rss: RssSettings,
     },
     interface: {
         theme: MantineColorScheme,
@@ -222,6 +310,7 @@ const DefaultColumnVisibility: Partial<Record<TableName, VisibilityState>> = {
         cachedFrom: false,
         cachedConnection: false,
         cachedProtocol: false,
+        cachedHostname: false,
     },
 } as const;
 
@@ -264,6 +353,20 @@ const DefaultSettings: Settings = {
         onClose: "quit",
         fontSizeBase: 0.9,
         fontSize: 0.9,
+        geoip: {
+            countryDb: "",
+            asnDb: "",
+            cityDb: "",
+            updateUrl: "",
+            updateIntervalDays: 30,
+        },
+        watchFolders: [],
+        clipboardMonitoring: false,
+        downloadSites: [],
+        rss: {
+            feeds: [],
+            rules: [],
+        },
     },
     interface: {
         theme: "auto",
@@ -373,7 +476,7 @@ export class Config {
         );
 
         this.values.servers = this.values.servers.map(
-            (s) => ({ ...s, connection: { ...s.connection, password: deobfuscate(s.connection.password) } }));
+            (s) => ({ ...s, connection: mapPasswords(s.connection, deobfuscate) }));
 
         if (this.values.app.lastTab >= this.values.app.openTabs.length) {
             this.values.app.lastTab = -1;
@@ -385,7 +488,7 @@ export class Config {
     async save() {
         const values = { ...this.values };
         values.servers = values.servers.map(
-            (s) => ({ ...s, connection: { ...s.connection, password: obfuscate(s.connection.password) } }));
+            (s) => ({ ...s, connection: mapPasswords(s.connection, obfuscate) }));
         const configText = JSON.stringify(values, null, "    ");
         await writeConfigText(configText);
     }
@@ -565,6 +668,18 @@ function deobfuscate(s: string) {
     }
 }
 
+// Proxy and client certificate passwords are stored the same way as the server password
+function mapPasswords(connection: ServerConnection, map: (s: string) => string): ServerConnection {
+    const result = { ...connection, password: map(connection.password) };
+    if (connection.proxy !== undefined) {
+        result.proxy = { ...connection.proxy, password: map(connection.proxy.password) };
+    }
+    if (connection.tls !== undefined) {
+        result.tls = { ...connection.tls, clientCertPassword: map(connection.tls.clientCertPassword) };
+    }
+    return result;
+}
+
 function b64EncodeUnicode(str: string) {
     return btoa(encodeURIComponent(str).replace(/%([0-9A-F]{2})/g, function (match, p1) {
         return String.fromCharCode(parseInt(p1, 16));
@@ -45,10 +45,15 @@ async function onCloseRequested(app: Root, config: Config) {
 }
 
 async function onFocusChange(focused: boolean, config: Config) {
-    if (!focused && config.values.app.onMinimize === "hide") {
+    if (!focused && config.values.app.onMinimize !== "minimize") {
         if (await appWindow.isMinimized()) {
-            void appWindow.emit("window-hidden");
-            void appWindow.hide();
+            if (config.values.app.onMinimize === "close") {
+                // Backend asks for shutdown through exit-requested and destroys the webview
+                void appWindow.emit("window-unload");
+            } else {
+                void appWindow.emit("window-hidden");
+                void appWindow.hide();
+            }
         }
     }
 }
@@ -175,6 +180,13 @@ async function run(config: Config) {
             void invoke("create_tray");
         }
 
+        void invoke("set_geoip_config", { config: config.values.app.geoip });
+        void invoke("set_watch_folders", { folders: config.values.app.watchFolders });
+        void invoke("set_clipboard_monitoring", {
+            enabled: config.values.app.clipboardMonitoring && config.values.app.showTrayIcon,
+        });
+        void invoke("set_rss_config", { config: config.values.app.rss });
+
         await restoreWindow(config);
     } else {
         setupWebEvents(config);
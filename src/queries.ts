@@ -22,8 +22,8 @@ import { ConfigContext, ServerConfigContext } from "config";
 import { useCallback, useContext, useEffect, useMemo, useState } from "react";
 import type { SessionInfo, TorrentActionMethodsType, TorrentAddParams } from "rpc/client";
 import { useTransmissionClient } from "rpc/client";
-import type { Torrent, TorrentBase } from "rpc/torrent";
-import { processTorrent } from "rpc/torrent";
+import type { PeerLookups, Torrent, TorrentBase } from "rpc/torrent";
+import { NoPeerLookups, processTorrent } from "rpc/torrent";
 import type { TorrentMutableFieldsType, TorrentFieldsType, TorrentAllFieldsType } from "rpc/transmission";
 const { TAURI, appWindow } = await import(/* webpackChunkName: "taurishim" */"taurishim");
 
@@ -105,12 +105,12 @@ export function useTorrentList(enabled: boolean, fields: TorrentFieldsType[]) {
         queryFn: useCallback(async () => {
             const torrents = await client.getTorrents(fields);
             return await Promise.all(torrents.map(
-                async (t: TorrentBase) => await processTorrent(t, false, prefixesRe, client)));
+                async (t: TorrentBase) => await processTorrent(t, NoPeerLookups, prefixesRe, client)));
         }, [client, fields, prefixesRe]),
     });
 }
 
-export function useTorrentDetails(torrentId: number, enabled: boolean, lookupIps: boolean, disableRefetch?: boolean) {
+export function useTorrentDetails(torrentId: number, enabled: boolean, lookups: PeerLookups, disableRefetch?: boolean) {
     const serverConfig = useContext(ServerConfigContext);
     const client = useTransmissionClient();
 
@@ -122,8 +122,9 @@ export function useTorrentDetails(torrentId: number, enabled: boolean, lookupIps
         staleTime: 1000 * 5,
         enabled,
         queryFn: useCallback(async () => {
-            return await processTorrent(await client.getTorrentDetails(torrentId), TAURI && lookupIps, prefixesRe, client);
-        }, [client, torrentId, lookupIps, prefixesRe]),
+            return await processTorrent(
+                await client.getTorrentDetails(torrentId), TAURI ? lookups : NoPeerLookups, prefixesRe, client);
+        }, [client, torrentId, lookups, prefixesRe]),
     });
 }
 
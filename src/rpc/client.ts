@@ -70,6 +70,15 @@ interface IpLookupResult {
     ip: string,
     isoCode?: string,
     name?: string,
+    asn?: number,
+    organization?: string,
+    city?: string,
+    region?: string,
+}
+
+interface RdnsResult {
+    ip: string,
+    hostname?: string,
 }
 
 export class TransmissionClient {
@@ -78,8 +87,8 @@ export class TransmissionClient {
     headers: Record<string, string>;
     timeout: number;
     sessionInfo: SessionInfo;
-    insecure: boolean;
     ipsBatcher: Batcher<IpLookupResult, string>;
+    rdnsBatcher: Batcher<RdnsResult, string>;
 
     constructor(connection: ServerConnection, toastNotifications: boolean, toastNotificationSound: boolean, timeout = 15) {
         this.url = encodeURIComponent(connection.url);
@@ -97,7 +106,16 @@ export class TransmissionClient {
             this.headers.Authorization = auth;
         }
         this.timeout = timeout;
-        this.insecure = connection.acceptInvalidCerts === true;
+        if (connection.url !== "") {
+            // Sent in a header so that proxy and certificate passwords don't end up in urls
+            this.headers["X-TrguiNG-Client"] = encodeURIComponent(JSON.stringify({
+                acceptInvalidCerts: connection.acceptInvalidCerts === true,
+                proxy: connection.proxy,
+                tls: connection.tls,
+                timeouts: connection.timeouts,
+                http2: connection.http2 === true,
+            }));
+        }
         this.sessionInfo = {};
         this.hostname = "unknown";
         try {
@@ -114,6 +132,12 @@ export class TransmissionClient {
             },
             resolver: keyResolver("ip"),
         });
+        this.rdnsBatcher = create<RdnsResult, string>({
+            fetcher: async (ips: string[]) => {
+                return await this.lookupIps(ips, "rdns");
+            },
+            resolver: keyResolver("ip"),
+        });
     }
 
     getHeader(headers: Record<string, string>, header: string) {
@@ -126,10 +150,9 @@ export class TransmissionClient {
     }
 
     async _sendRpc(data: Record<string, unknown>) {
-        const insecureParam = this.insecure ? "&insecure=true" : "";
         const url = this.url === ""
             ? "../rpc"
-            : `${RUST_BACKEND}/${data.method === "torrent-get" ? "torrentget" : "post"}?url=${this.url}${insecureParam}`;
+            : `${RUST_BACKEND}/${data.method === "torrent-get" ? "torrentget" : "post"}?url=${this.url}`;
         const body = JSON.stringify(data);
         let response = await fetch(
             url, { method: "POST", redirect: "manual", headers: this.headers, body });
@@ -154,6 +177,10 @@ export class TransmissionClient {
             }
 
             return responseJson;
+        } else if (response.headers.get("X-TrguiNG-Error") === "timeout") {
+            throw new Error("Server did not respond in time");
+        } else if (response.headers.get("X-TrguiNG-Error") === "connect") {
+            throw new Error(await response.text());
         } else if (response.type === "opaqueredirect") {
             throw new Error("Server makes a redirect");
         } else if (response.type === "error") {
@@ -394,8 +421,8 @@ export class TransmissionClient {
         });
     }
 
-    async lookupIps(ips: string[]) {
-        const url = `${RUST_BACKEND}/iplookup`;
+    async lookupIps(ips: string[], endpoint: "iplookup" | "rdns" = "iplookup") {
+        const url = `${RUST_BACKEND}/${endpoint}`;
         const body = JSON.stringify(ips);
 
         const response = await fetch(url, { method: "POST", body, headers: { "Content-Type": "application/json" } });
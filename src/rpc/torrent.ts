@@ -120,10 +120,17 @@ function getPeersTotal(t: TorrentBase) {
     return peers;
 }
 
-export async function processTorrent(t: TorrentBase, lookupIps: boolean, ignoredPrefixesRe: RegExp, client: TransmissionClient): Promise<Torrent> {
+export interface PeerLookups {
+    geoip: boolean,
+    hostnames: boolean,
+}
+
+export const NoPeerLookups: PeerLookups = { geoip: false, hostnames: false };
+
+export async function processTorrent(t: TorrentBase, lookups: PeerLookups, ignoredPrefixesRe: RegExp, client: TransmissionClient): Promise<Torrent> {
     const peers = t.peers === undefined
         ? undefined
-        : await Promise.all(t.peers.map(async (p: PeerStatsBase) => await processPeerStats(p, lookupIps, client)));
+        : await Promise.all(t.peers.map(async (p: PeerStatsBase) => await processPeerStats(p, lookups, client)));
 
     return {
         ...t,
@@ -174,6 +181,9 @@ export interface PeerStats extends PeerStatsBase {
     cachedStatus: string,
     cachedCountryIso?: string,
     cachedCountryName?: string,
+    cachedOrganization?: string,
+    cachedLocation?: string,
+    cachedHostname?: string,
 }
 
 // Flag meanings: https://github.com/transmission/transmission/blob/main/docs/Peer-Status-Text.md
@@ -188,7 +198,7 @@ const statusFlagStrings = {
     "?": "peer not interested",
 } as const;
 
-async function processPeerStats(peer: PeerStatsBase, lookupIps: boolean, client: TransmissionClient): Promise<PeerStats> {
+async function processPeerStats(peer: PeerStatsBase, lookups: PeerLookups, client: TransmissionClient): Promise<PeerStats> {
     const flags = peer.flagStr as string;
 
     const cachedFrom = flags.includes("X")
@@ -200,9 +210,10 @@ async function processPeerStats(peer: PeerStatsBase, lookupIps: boolean, client:
     const status = [...flags.matchAll(/[ODdUuK?]/g)].map(
         (s) => statusFlagStrings[s[0] as keyof (typeof statusFlagStrings)]);
 
-    const country = lookupIps
-        ? await client.ipsBatcher.fetch(peer.address)
-        : undefined;
+    const [country, rdns] = await Promise.all([
+        lookups.geoip ? client.ipsBatcher.fetch(peer.address) : undefined,
+        lookups.hostnames ? client.rdnsBatcher.fetch(peer.address) : undefined,
+    ]);
 
     return {
         ...peer,
@@ -213,5 +224,10 @@ async function processPeerStats(peer: PeerStatsBase, lookupIps: boolean, client:
         cachedStatus: (status ?? []).join(", "),
         cachedCountryIso: country?.isoCode,
         cachedCountryName: country?.name,
+        cachedOrganization: country?.organization !== undefined
+            ? `${country.organization} (AS${country.asn ?? "?"})`
+            : undefined,
+        cachedLocation: [country?.city, country?.region].filter((s) => s !== undefined).join(", "),
+        cachedHostname: rdns?.hostname,
     };
 }